@@ -18,7 +18,8 @@ fn main() {
         width,
         rotation_angle,
     )
-    .expect("Could not rotate sprite");
+    .expect("Could not rotate sprite")
+    .into();
 
     let rotated_image = RgbaImage::from_fn(rotated_width as u32, rotated_height as u32, |x, y| {
         rotated[rotated_width * y as usize + x as usize]
@@ -0,0 +1,118 @@
+/// 2D affine transformation stored as a 2x3 matrix.
+///
+/// A point `(x, y)` is mapped to `(a * x + b * y + c, d * x + e * y + f)` where the matrix is `[[a, b, c], [d, e, f]]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine(pub [[f64; 3]; 2]);
+
+impl Affine {
+    /// Transformation that doesn't change anything.
+    pub const IDENTITY: Self = Self([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+
+    /// Rotate clockwise around the origin, the rotation is in degrees.
+    ///
+    /// Clockwise because the Y axis points down in pixel buffers.
+    #[must_use]
+    pub fn rotation(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+
+        Self([[cos, -sin, 0.0], [sin, cos, 0.0]])
+    }
+
+    /// Scale relative to the origin.
+    #[must_use]
+    pub fn scale(x: f64, y: f64) -> Self {
+        Self([[x, 0.0, 0.0], [0.0, y, 0.0]])
+    }
+
+    /// Move all points.
+    #[must_use]
+    pub fn translation(x: f64, y: f64) -> Self {
+        Self([[1.0, 0.0, x], [0.0, 1.0, y]])
+    }
+
+    /// Combine with another transformation that's applied after this one.
+    #[must_use]
+    pub fn then(self, next: Self) -> Self {
+        let [[a, b, c], [d, e, f]] = self.0;
+        let [[na, nb, nc], [nd, ne, nf]] = next.0;
+
+        Self([
+            [na * a + nb * d, na * b + nb * e, na * c + nb * f + nc],
+            [nd * a + ne * d, nd * b + ne * e, nd * c + ne * f + nf],
+        ])
+    }
+
+    /// Transformation that undoes this one.
+    ///
+    /// Returns `None` when the matrix can't be inverted, for example when it scales to zero or contains NaN or infinite values.
+    #[must_use]
+    pub fn inverse(self) -> Option<Self> {
+        if !self.0.iter().flatten().all(|value| value.is_finite()) {
+            return None;
+        }
+
+        let [[a, b, c], [d, e, f]] = self.0;
+
+        let determinant = a * e - b * d;
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+
+        let (ia, ib, id, ie) = (
+            e / determinant,
+            -b / determinant,
+            -d / determinant,
+            a / determinant,
+        );
+
+        Some(Self([
+            [ia, ib, -(ia * c + ib * f)],
+            [id, ie, -(id * c + ie * f)],
+        ]))
+    }
+
+    /// Map a point `(x, y)`.
+    #[must_use]
+    pub fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let [[a, b, c], [d, e, f]] = self.0;
+
+        (a * x + b * y + c, d * x + e * y + f)
+    }
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close((x1, y1): (f64, f64), (x2, y2): (f64, f64)) {
+        assert!(
+            (x1 - x2).abs() < 1e-9 && (y1 - y2).abs() < 1e-9,
+            "({x1}, {y1}) != ({x2}, {y2})"
+        );
+    }
+
+    #[test]
+    fn compose() {
+        let transform = Affine::scale(2.0, 3.0)
+            .then(Affine::rotation(90.0))
+            .then(Affine::translation(1.0, 1.0));
+        assert_close(transform.apply((1.0, 1.0)), (-2.0, 3.0));
+    }
+
+    #[test]
+    fn inverse() {
+        let transform = Affine::rotation(33.0)
+            .then(Affine::scale(1.5, 0.5))
+            .then(Affine::translation(-4.0, 2.0));
+        let inverse = transform.inverse().unwrap();
+        assert_close(inverse.apply(transform.apply((3.0, -7.0))), (3.0, -7.0));
+
+        assert_eq!(Affine::scale(0.0, 1.0).inverse(), None);
+    }
+}
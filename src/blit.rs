@@ -1,18 +1,39 @@
 use blit::BlitBuffer;
 
-use crate::{Error, Rotsprite};
+use crate::{Error, ImageView, ImageViewMut, Rotsprite};
 
 impl Rotsprite<u32> for BlitBuffer {
     fn rotsprite(&self, rotation: f64) -> Result<Self, Error> {
         // Rotate the current blitbuffer, using 0x00_00_00_00 for the empty color
-        let (width, _, pixels) = crate::rotsprite(
-            self.pixels(),
-            &0x00_00_00_00,
-            self.width() as usize,
-            rotation,
-        )?;
+        let (width, _, pixels) = crate::rotsprite_view(self, &0x00_00_00_00, rotation)?.into();
 
         // Create a new blitbuffer from the pixels
         Ok(Self::from_iter(pixels.into_iter(), width as i32, 1))
     }
 }
+
+impl ImageView<u32> for BlitBuffer {
+    fn width(&self) -> usize {
+        BlitBuffer::width(self) as usize
+    }
+
+    fn height(&self) -> usize {
+        BlitBuffer::height(self) as usize
+    }
+
+    #[inline(always)]
+    fn row(&self, y: usize) -> &[u32] {
+        let width = ImageView::width(self);
+
+        &self.pixels()[y * width..(y + 1) * width]
+    }
+}
+
+impl ImageViewMut<u32> for BlitBuffer {
+    #[inline(always)]
+    fn row_mut(&mut self, y: usize) -> &mut [u32] {
+        let width = ImageView::width(self);
+
+        &mut self.pixels_mut()[y * width..(y + 1) * width]
+    }
+}
@@ -0,0 +1,203 @@
+/// Decide whether two pixels should be treated as the same color.
+///
+/// Implemented for any closure with the signature `Fn(&P, &P) -> bool`.
+pub trait Similarity<P> {
+    /// Whether both pixels are considered equal.
+    fn is_similar(&self, a: &P, b: &P) -> bool;
+}
+
+impl<P, F> Similarity<P> for F
+where
+    F: Fn(&P, &P) -> bool,
+{
+    #[inline(always)]
+    fn is_similar(&self, a: &P, b: &P) -> bool {
+        self(a, b)
+    }
+}
+
+/// Only treat pixels as similar when they are exactly equal.
+///
+/// This is the default behaviour of the scale2x algorithm.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Exact;
+
+impl<P> Similarity<P> for Exact
+where
+    P: Eq,
+{
+    #[inline(always)]
+    fn is_similar(&self, a: &P, b: &P) -> bool {
+        a == b
+    }
+}
+
+/// Treat pixels as similar when the euclidean distance between their RGBA channels is at most `threshold`.
+///
+/// A threshold of `0` behaves the same as [`Exact`], the maximum possible distance is `510`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RgbaDistance {
+    /// Maximum distance between two colors that are still considered equal.
+    pub threshold: u32,
+}
+
+impl RgbaDistance {
+    /// Treat colors within the `threshold` distance as equal.
+    #[must_use]
+    pub fn new(threshold: u32) -> Self {
+        Self { threshold }
+    }
+}
+
+impl<P> Similarity<P> for RgbaDistance
+where
+    P: RgbaPixel,
+{
+    #[inline(always)]
+    fn is_similar(&self, a: &P, b: &P) -> bool {
+        let (a, b) = (a.to_rgba(), b.to_rgba());
+
+        let distance_squared: u32 = a
+            .iter()
+            .zip(b.iter())
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+            .sum();
+
+        // Square in `u64` so large thresholds don't overflow
+        u64::from(distance_squared) <= u64::from(self.threshold).pow(2)
+    }
+}
+
+/// Measure how far two pixels are apart, used for finding the closest color.
+///
+/// Implemented for any closure with the signature `Fn(&P, &P) -> f64`.
+pub trait ColorDistance<P> {
+    /// Distance between both pixels, lower is closer.
+    fn distance(&self, a: &P, b: &P) -> f64;
+
+    /// Single value per pixel used to skip colors when searching for the closest one.
+    ///
+    /// The distance between two pixels must never be smaller than the difference between their keys.
+    /// The default of `0.0` for every pixel never skips any colors.
+    fn key(&self, _pixel: &P) -> f64 {
+        0.0
+    }
+}
+
+impl<P, F> ColorDistance<P> for F
+where
+    F: Fn(&P, &P) -> f64,
+{
+    #[inline(always)]
+    fn distance(&self, a: &P, b: &P) -> f64 {
+        self(a, b)
+    }
+}
+
+/// Euclidean distance between the RGBA channels of two pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EuclideanDistance;
+
+impl<P> ColorDistance<P> for EuclideanDistance
+where
+    P: RgbaPixel,
+{
+    #[inline(always)]
+    fn distance(&self, a: &P, b: &P) -> f64 {
+        let (a, b) = (a.to_rgba(), b.to_rgba());
+
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| (f64::from(*a) - f64::from(*b)).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    #[inline(always)]
+    fn key(&self, pixel: &P) -> f64 {
+        // The sum of the differences of 4 channels is at most twice their euclidean length
+        pixel
+            .to_rgba()
+            .iter()
+            .map(|channel| f64::from(*channel))
+            .sum::<f64>()
+            / 2.0
+    }
+}
+
+/// Pixel format with red, green, blue and alpha channels.
+pub trait RgbaPixel {
+    /// Get the channels as `[red, green, blue, alpha]`.
+    fn to_rgba(&self) -> [u8; 4];
+
+    /// Construct from the channels `[red, green, blue, alpha]`.
+    fn from_rgba(rgba: [u8; 4]) -> Self;
+}
+
+/// Pixels packed as `0xAA_RR_GG_BB`, the same layout as used by the `blit` crate.
+impl RgbaPixel for u32 {
+    #[inline(always)]
+    fn to_rgba(&self) -> [u8; 4] {
+        let [a, r, g, b] = self.to_be_bytes();
+
+        [r, g, b, a]
+    }
+
+    #[inline(always)]
+    fn from_rgba([r, g, b, a]: [u8; 4]) -> Self {
+        u32::from_be_bytes([a, r, g, b])
+    }
+}
+
+impl RgbaPixel for [u8; 4] {
+    #[inline(always)]
+    fn to_rgba(&self) -> [u8; 4] {
+        *self
+    }
+
+    #[inline(always)]
+    fn from_rgba(rgba: [u8; 4]) -> Self {
+        rgba
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgba_distance() {
+        let similarity = RgbaDistance::new(10);
+        assert!(similarity.is_similar(&0xFF_10_10_10_u32, &0xFF_10_10_10_u32));
+        assert!(similarity.is_similar(&0xFF_10_10_10_u32, &0xFF_16_18_10_u32));
+        assert!(!similarity.is_similar(&0xFF_10_10_10_u32, &0xFF_20_10_10_u32));
+        assert!(!similarity.is_similar(&[0, 0, 0, 255], &[0, 0, 0, 0]));
+
+        let exact = RgbaDistance::new(0);
+        assert!(exact.is_similar(&[1, 2, 3, 4], &[1, 2, 3, 4]));
+        assert!(!exact.is_similar(&[1, 2, 3, 4], &[1, 2, 3, 5]));
+
+        // Thresholds larger than any distance don't overflow when squared
+        let any = RgbaDistance::new(u32::MAX);
+        assert!(any.is_similar(&[0, 0, 0, 0], &[255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn euclidean_distance() {
+        assert_eq!(
+            EuclideanDistance.distance(&[1, 2, 3, 4], &[1, 2, 3, 4]),
+            0.0
+        );
+        assert_eq!(
+            EuclideanDistance.distance(&0xFF_00_00_00_u32, &0xFF_03_04_00),
+            5.0
+        );
+    }
+
+    #[test]
+    fn closure_similarity() {
+        let similarity = |a: &i32, b: &i32| (a - b).abs() <= 1;
+        assert!(similarity.is_similar(&1, &2));
+        assert!(!similarity.is_similar(&1, &3));
+    }
+}
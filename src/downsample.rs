@@ -0,0 +1,194 @@
+use crate::{Downsample, RgbaPixel, TieBreak};
+
+/// Samples of the upscaled image covered by a single output pixel.
+#[derive(Debug)]
+pub struct Block<'a, P> {
+    /// Samples as `(x, y, pixel)` with the position inside the block, row by row.
+    samples: &'a [(usize, usize, &'a P)],
+    /// Size of a full block in each dimension.
+    factor: usize,
+    /// Sample a nearest-neighbour downscale picks.
+    nearest: &'a P,
+    /// Color of samples outside of the source.
+    empty_color: &'a P,
+}
+
+impl<'a, P> Block<'a, P> {
+    /// Construct from all parts.
+    pub(crate) fn new(
+        samples: &'a [(usize, usize, &'a P)],
+        factor: usize,
+        nearest: &'a P,
+        empty_color: &'a P,
+    ) -> Self {
+        Self {
+            samples,
+            factor,
+            nearest,
+            empty_color,
+        }
+    }
+
+    /// Samples as `(x, y, pixel)` with the position inside the block, row by row.
+    ///
+    /// Samples outside of the source are the empty color.
+    /// Blocks at the right and bottom edges of the output can contain fewer than `factor * factor` samples.
+    pub fn samples(&self) -> &'a [(usize, usize, &'a P)] {
+        self.samples
+    }
+
+    /// Size of a full block in each dimension, the same as the upscale factor.
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Sample picked by a nearest-neighbour downscale.
+    pub fn nearest(&self) -> &'a P {
+        self.nearest
+    }
+
+    /// Color of samples outside of the source.
+    pub fn empty_color(&self) -> &'a P {
+        self.empty_color
+    }
+
+    /// Manhattan distance of a position inside the block to the center of the block, in half samples.
+    pub fn center_distance(&self, x: usize, y: usize) -> usize {
+        (x * 2 + 1).abs_diff(self.factor) + (y * 2 + 1).abs_diff(self.factor)
+    }
+}
+
+/// Algorithm reducing the samples of the upscaled rotated image to a single output pixel.
+///
+/// Implement this to try other strategies than the ones provided, see [`crate::RotspriteOptions::with_downsample`] for using it.
+pub trait Downsampler<P> {
+    /// Reduce all samples covered by an output pixel to its color.
+    fn downsample(&self, block: &Block<'_, P>) -> P;
+
+    /// Whether the output pixel is always [`Block::nearest`].
+    ///
+    /// Rotating is faster when this is `true` because the samples of a block don't have to be collected.
+    fn is_nearest(&self) -> bool {
+        false
+    }
+}
+
+impl<P> Downsampler<P> for Downsample
+where
+    P: Eq + Clone,
+{
+    fn downsample(&self, block: &Block<'_, P>) -> P {
+        match self {
+            Downsample::Nearest => block.nearest().clone(),
+            Downsample::Center => block
+                .samples()
+                .iter()
+                .min_by_key(|(x, y, _)| block.center_distance(*x, *y))
+                .map_or(block.empty_color(), |(_, _, pixel)| *pixel)
+                .clone(),
+            Downsample::Majority(tie_break) => majority(block, *tie_break).clone(),
+        }
+    }
+
+    fn is_nearest(&self) -> bool {
+        *self == Downsample::Nearest
+    }
+}
+
+/// Average the color channels weighted by their alpha, and average the alpha.
+///
+/// Smooths the edges of the rotated sprite, at the cost of introducing new colors.
+/// When all samples are fully transparent the empty color is used.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AlphaWeighted;
+
+impl<P> Downsampler<P> for AlphaWeighted
+where
+    P: RgbaPixel + Clone,
+{
+    fn downsample(&self, block: &Block<'_, P>) -> P {
+        let samples = block.samples();
+
+        let mut sums = [0_u64; 4];
+        for (_, _, pixel) in samples {
+            let [r, g, b, a] = pixel.to_rgba().map(u64::from);
+            sums[0] += r * a;
+            sums[1] += g * a;
+            sums[2] += b * a;
+            sums[3] += a;
+        }
+
+        let total_alpha = sums[3];
+        if total_alpha == 0 {
+            return block.empty_color().clone();
+        }
+
+        // Round to the closest value
+        let average = |sum: u64, total: u64| ((sum + total / 2) / total) as u8;
+
+        P::from_rgba([
+            average(sums[0], total_alpha),
+            average(sums[1], total_alpha),
+            average(sums[2], total_alpha),
+            average(total_alpha, samples.len() as u64),
+        ])
+    }
+}
+
+/// Pick the most frequent color in a block.
+fn majority<'a, P>(block: &Block<'a, P>, tie_break: TieBreak) -> &'a P
+where
+    P: Eq,
+{
+    // Count each color and keep track of its sample closest to the center
+    let mut counts: Vec<(&P, usize, usize)> = Vec::new();
+    for &(x, y, pixel) in block.samples() {
+        let distance = block.center_distance(x, y);
+        match counts.iter_mut().find(|(color, _, _)| *color == pixel) {
+            Some((_, count, closest)) => {
+                *count += 1;
+                *closest = (*closest).min(distance);
+            }
+            None => counts.push((pixel, 1, distance)),
+        }
+    }
+
+    counts
+        .iter()
+        .min_by_key(|(color, count, closest)| {
+            let empty = match tie_break {
+                TieBreak::Center => false,
+                TieBreak::NonEmpty => *color == block.empty_color(),
+            };
+
+            (std::cmp::Reverse(*count), empty, *closest)
+        })
+        .map_or(block.empty_color(), |(color, _, _)| *color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsamplers() {
+        #[rustfmt::skip]
+        let samples = [
+            (0, 0, &0_u32), (1, 0, &0xFF_00_00_FF), (2, 0, &0xFF_00_00_FF),
+            (0, 1, &0), (1, 1, &0x80_FF_00_00), (2, 1, &0),
+            (0, 2, &0), (1, 2, &0), (2, 2, &0),
+        ];
+        let block = Block::new(&samples, 3, &0, &0);
+
+        assert_eq!(Downsample::Nearest.downsample(&block), 0);
+        assert_eq!(Downsample::Center.downsample(&block), 0x80_FF_00_00);
+        assert_eq!(Downsample::Majority(TieBreak::Center).downsample(&block), 0);
+
+        // The opaque blue outweighs the translucent red, the alpha is averaged over all samples
+        assert_eq!(AlphaWeighted.downsample(&block), 0x47_33_00_CC);
+        assert_eq!(
+            AlphaWeighted.downsample(&Block::new(&samples[..1], 3, &0, &0)),
+            0
+        );
+    }
+}
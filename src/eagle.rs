@@ -0,0 +1,88 @@
+use crate::{
+    color::{Exact, Similarity},
+    upscale::{upscale_blocks, upscale_packed},
+    Border, Error, ImageView, RotationResult,
+};
+
+// Eagle algorithm for upscaling pixel art sprites
+//
+// Every corner of the upscaled pixel takes the color of the three neighbours in that direction when they're equal
+pub fn eagle<P>(buf: &[P], width: usize, height: usize) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+{
+    upscale_packed(buf, width, height, |image, border, pixel| {
+        eagle_with_border(image, &Exact, border, pixel)
+    })
+}
+
+// Same as `eagle` but neighbouring pixels are compared with a custom predicate
+//
+// Neighbours outside of the image are resolved with the border mode, the empty color is only used by `Border::Empty`
+pub fn eagle_with_border<P, S, I>(
+    image: &I,
+    similarity: &S,
+    border: Border,
+    empty_color: &P,
+) -> RotationResult<P>
+where
+    P: Clone,
+    S: Similarity<P>,
+    I: ImageView<P>,
+{
+    upscale_blocks(image, border, empty_color, |neighbourhood| {
+        calculate_eagle_block(similarity, neighbourhood)
+    })
+}
+
+// Convert a single pixel with its 8 neighbours to an upscaled 2x2 block
+#[inline(always)]
+fn calculate_eagle_block<P, S>(
+    similarity: &S,
+    [[a, b, c], [d, e, f], [g, h, i]]: [[&P; 3]; 3],
+) -> [[P; 2]; 2]
+where
+    P: Clone,
+    S: Similarity<P>,
+{
+    let eq = |a, b| similarity.is_similar(a, b);
+    let corner = |side1, diagonal, side2| {
+        if eq(side1, diagonal) && eq(diagonal, side2) {
+            diagonal
+        } else {
+            e
+        }
+        .clone()
+    };
+
+    [
+        [corner(b, a, d), corner(b, c, f)],
+        [corner(d, g, h), corner(f, i, h)],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eagle_test() -> Result<(), Error> {
+        let (w, h, new) = eagle(&[1, 2, 3, 4], 2, 2)?.into();
+        assert_eq!((w, h), (4, 4));
+        assert_eq!(new, [1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]);
+
+        // Only the corner surrounded by the same color changes
+        #[rustfmt::skip]
+        let buf = [
+            1, 1, 0,
+            1, 0, 0,
+            0, 0, 0,
+        ];
+        let new = eagle(&buf, 3, 3)?.into_pixels();
+        assert_eq!(new[2 * 6 + 2], 1);
+        assert_eq!(new[2 * 6 + 3], 0);
+        assert_eq!(new[3 * 6 + 2], 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    color::{Exact, Similarity},
+    upscale::{upscale_blocks, upscale_packed},
+    Border, Error, ImageView, RotationResult,
+};
+
+// Eric's Pixel Expansion, the algorithm scale2x is based on
+//
+// Unlike scale2x it also fills corners where the neighbours on the opposite sides are equal,
+// which results in bolder diagonals
+pub fn epx<P>(buf: &[P], width: usize, height: usize) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+{
+    upscale_packed(buf, width, height, |image, border, pixel| {
+        epx_with_border(image, &Exact, border, pixel)
+    })
+}
+
+// Same as `epx` but neighbouring pixels are compared with a custom predicate
+//
+// Neighbours outside of the image are resolved with the border mode, the empty color is only used by `Border::Empty`
+pub fn epx_with_border<P, S, I>(
+    image: &I,
+    similarity: &S,
+    border: Border,
+    empty_color: &P,
+) -> RotationResult<P>
+where
+    P: Clone,
+    S: Similarity<P>,
+    I: ImageView<P>,
+{
+    upscale_blocks(
+        image,
+        border,
+        empty_color,
+        |[[_, up, _], [left, center, right], [_, down, _]]| {
+            calculate_epx_block(similarity, center, up, left, down, right)
+        },
+    )
+}
+
+// Convert a single pixel to an upscaled 2x2 block
+#[inline(always)]
+fn calculate_epx_block<P, S>(
+    similarity: &S,
+    center: &P,
+    up: &P,
+    left: &P,
+    down: &P,
+    right: &P,
+) -> [[P; 2]; 2]
+where
+    P: Clone,
+    S: Similarity<P>,
+{
+    let eq = |a, b| similarity.is_similar(a, b);
+
+    // When three or more neighbours are the same the pixel is kept as is
+    let same = [
+        eq(up, left),
+        eq(up, right),
+        eq(up, down),
+        eq(left, right),
+        eq(left, down),
+        eq(right, down),
+    ]
+    .into_iter()
+    .filter(|same| *same)
+    .count();
+    if same >= 3 {
+        return [
+            [center.clone(), center.clone()],
+            [center.clone(), center.clone()],
+        ];
+    }
+
+    let pick = |condition: bool, pixel: &P| if condition { pixel } else { center }.clone();
+
+    [
+        [pick(eq(left, up), up), pick(eq(up, right), right)],
+        [pick(eq(down, left), left), pick(eq(right, down), down)],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epx_test() -> Result<(), Error> {
+        let (w, h, new) = epx(&[1, 2, 3, 4], 2, 2)?.into();
+        assert_eq!((w, h), (4, 4));
+        assert_eq!(new, [1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]);
+
+        // Corners are filled when the neighbours are equal
+        #[rustfmt::skip]
+        let buf = [
+            0, 1, 0,
+            1, 0, 2,
+            0, 3, 0,
+        ];
+        let new = epx(&buf, 3, 3)?.into_pixels();
+        assert_eq!(new[2 * 6 + 2], 1);
+        assert_eq!(new[2 * 6 + 3], 0);
+        assert_eq!(new[3 * 6 + 2], 0);
+
+        Ok(())
+    }
+}
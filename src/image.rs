@@ -0,0 +1,15 @@
+use image::Rgba;
+
+use crate::RgbaPixel;
+
+impl RgbaPixel for Rgba<u8> {
+    #[inline(always)]
+    fn to_rgba(&self) -> [u8; 4] {
+        self.0
+    }
+
+    #[inline(always)]
+    fn from_rgba(rgba: [u8; 4]) -> Self {
+        Rgba(rgba)
+    }
+}
@@ -2,27 +2,87 @@
 //!
 //! This library allows you to rotate pixel art using the [rotsprite](https://en.wikipedia.org/wiki/Pixel-art_scaling_algorithms#RotSprite) algorithm.
 //!
+//! # Anti-aliasing
+//!
+//! By default only colors of the source sprite end up in the rotated sprite, giving hard pixel-art edges.
+//! For presenting sprites at high resolutions [`RotspriteOptions::with_anti_aliasing`] averages the upscaled samples covering each output pixel, weighted by their alpha.
+//! To smooth the shape while keeping the palette of the sprite, combine it with [`RotspriteOptions::with_palette`] and [`SourcePalette`].
+//!
 //! # Feature Flags
 //!
 //! ## `blit`
 //!
-//! Implement the [`crate::RotSprite`] trait for [`blit::BlitBuffer`], making it easy to get a rotated copy of a blit buffer.
+//! Implement the [`crate::Rotsprite`] trait for [`blit::BlitBuffer`](::blit::BlitBuffer), making it easy to get a rotated copy of a blit buffer.
+//! It also implements [`crate::ImageView`] and [`crate::ImageViewMut`], so blit buffers can be passed to functions like [`crate::rotsprite_view`] directly.
+//!
+//! ## `image`
+//!
+//! Implement [`crate::RgbaPixel`] for [`image::Rgba<u8>`](https://docs.rs/image/latest/image/struct.Rgba.html), so color based options like [`crate::SourcePalette`] work with pixels of the `image` crate.
 
 // Make the modules public for benchmarks but don't document it
+mod affine;
 #[cfg(feature = "blit")]
 mod blit;
+mod color;
+mod downsample;
+#[doc(hidden)]
+pub mod eagle;
+#[doc(hidden)]
+pub mod epx;
+#[cfg(feature = "image")]
+mod image;
+mod mapping;
+mod options;
+mod palette;
+#[doc(hidden)]
+pub mod restore;
+mod result;
 #[doc(hidden)]
 pub mod rotate;
 #[doc(hidden)]
 pub mod scale2x;
+#[doc(hidden)]
+pub mod scale3x;
+mod sub_image;
+mod upscale;
+mod view;
+#[doc(hidden)]
+pub mod xbr;
 
-use crate::{rotate::*, scale2x::*};
+use crate::{restore::*, rotate::*, view::RowOrder};
 use thiserror::Error;
 
+pub use affine::Affine;
+pub use color::{ColorDistance, EuclideanDistance, Exact, RgbaDistance, RgbaPixel, Similarity};
+pub use downsample::{AlphaWeighted, Block, Downsampler};
+pub use mapping::PointMapping;
+pub use options::{Border, Downsample, Flip, OutputSize, RotspriteOptions, TieBreak, Upscale};
+pub use palette::{AnyColor, ColorConstraint, SourcePalette};
+pub use result::RotationResult;
+pub use sub_image::{SubImage, SubImageMut};
+pub use upscale::{Eagle, Epx, Repeat, Scale2x, Upscaler, Xbr};
+pub use view::{ImageView, ImageViewMut};
+
 #[derive(Error, Debug, PartialEq)]
 pub enum Error {
     #[error("image size doesn't match with supplied width")]
     ImageSizeMismatch,
+    #[error("rectangle doesn't fit in the image")]
+    InvalidRect,
+    #[error("width can't be zero")]
+    ZeroWidth,
+    #[error("image buffer is empty")]
+    EmptyBuffer,
+    #[error("rotation must be a finite number of degrees")]
+    InvalidAngle,
+    #[error("scale must be a positive finite number")]
+    InvalidScale,
+    #[error("transformation matrix can't be inverted")]
+    InvalidTransform,
+    #[error("image is too large to rotate")]
+    TooLarge,
+    #[error("upscale factor can't be zero")]
+    ZeroFactor,
 }
 
 /// Expose `rotsprite` method on some image types.
@@ -32,7 +92,7 @@ where
 {
     /// Clone and rotate a sprite.
     ///
-    /// Rotation is in degrees (0-360).
+    /// Rotation is in degrees, any finite angle is normalised to 0-360 so `-90.0` is the same as `270.0`.
     /// The size of the resulting vector will be bigger if the rotation isn't exactly 0.0, 90.0, 180.0 or 270.0 degrees.
     /// The width and the height will be swapped at angles of 90.0 and 270.0.
     fn rotsprite(&self, rotation: f64) -> Result<Self, Error>
@@ -42,52 +102,612 @@ where
 
 /// Rotate a sprite based on any pixel format implementing the `Eq` and `Clone` traits.
 ///
-/// Rotation is in degrees (0-360).
+/// Rotation is in degrees, any finite angle is normalised to 0-360 so `-90.0` is the same as `270.0`.
+/// NaN and infinite angles return [`Error::InvalidAngle`].
 /// The size of the resulting vector will be bigger if the rotation isn't exactly 0.0, 90.0, 180.0 or 270.0 degrees.
 /// The width and the height will be swapped at angles of 90.0 and 270.0.
+pub fn rotsprite<P>(
+    buf: &[P],
+    empty_color: &P,
+    width: usize,
+    rotation: f64,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+{
+    rotsprite_with_options(buf, empty_color, width, rotation, &RotspriteOptions::new())
+}
+
+/// Rotate and scale a sprite at the same time.
+///
+/// Scale is uniform and can be any positive number, so `1.5` results in a sprite one and a half times as big.
+/// Sampling the upscaled image at the scaled rate keeps the pixel-art look, which doesn't happen when a rotated sprite is resized afterwards.
+pub fn rotsprite_scaled<P>(
+    buf: &[P],
+    empty_color: &P,
+    width: usize,
+    rotation: f64,
+    scale: f64,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+{
+    rotsprite_with_options(
+        buf,
+        empty_color,
+        width,
+        rotation,
+        &RotspriteOptions::new().with_scale(scale),
+    )
+}
+
+/// Rotate a sprite like [`rotsprite`] but with custom options.
+///
+/// See [`RotspriteOptions`] for what can be configured.
+pub fn rotsprite_with_options<P, S, U, D, C>(
+    buf: &[P],
+    empty_color: &P,
+    width: usize,
+    rotation: f64,
+    options: &RotspriteOptions<S, U, D, C>,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+    S: Similarity<P>,
+    U: Upscaler<P>,
+    D: Downsampler<P>,
+    C: ColorConstraint<P>,
+{
+    rotsprite_view_with_options(&SubImage::new(buf, width)?, empty_color, rotation, options)
+}
+
+/// Rotate a sprite like [`rotsprite`] from any [`ImageView`] instead of a buffer.
+///
+/// The pixels are read in place, so a sprite in a texture atlas can be selected with [`SubImage::from_rect`] or a texture type of your own can implement [`ImageView`].
+pub fn rotsprite_view<P, I>(
+    image: &I,
+    empty_color: &P,
+    rotation: f64,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+    I: ImageView<P>,
+{
+    rotsprite_view_with_options(image, empty_color, rotation, &RotspriteOptions::new())
+}
+
+/// Rotate a sprite like [`rotsprite_view`] but with custom options.
+///
+/// See [`RotspriteOptions`] for what can be configured.
 #[multiversion::multiversion(
     targets("x86_64+sse3", "x86_64+sse3+avx", "x86_64+sse3+avx2"),
     dispatcher = "static"
 )]
-pub fn rotsprite<P>(
+pub fn rotsprite_view_with_options<P, S, U, D, C, I>(
+    image: &I,
+    empty_color: &P,
+    rotation: f64,
+    options: &RotspriteOptions<S, U, D, C>,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+    S: Similarity<P>,
+    U: Upscaler<P>,
+    D: Downsampler<P>,
+    C: ColorConstraint<P>,
+    I: ImageView<P>,
+{
+    let (width, height) = image.size();
+    if width == 0 {
+        return Err(Error::ZeroWidth);
+    }
+    if height == 0 {
+        return Err(Error::EmptyBuffer);
+    }
+    let rotation = normalize_rotation(rotation)?;
+
+    check_factor::<P, S, U, D, C>(options)?;
+    check_scale(options.scale, options.upscale.factor())?;
+    check_canvas_size::<P, S, U, D, C>(options)?;
+
+    // Checked for every angle so the limit only depends on the size of the sprite
+    let scale = options.scale;
+    let matrix = Affine::rotation(rotation).then(Affine::scale(scale, scale));
+    check_sizes((width, height), matrix, options)?;
+
+    // If there's no rotation we don't have to do anything
+    let rotated = if rotation == 0.0 && scale == 1.0 {
+        let matrix = options.flip.matrix(width, height);
+        let pivot = matrix.apply(options.pivot_or_center(width, height));
+        let source = options.flip.apply(image).unwrap_or_else(|| image.to_vec());
+
+        RotationResult::new((width, height, source), 0.0, matrix, pivot)
+    } else {
+        rotsprite_expanded(image, empty_color, rotation, options)
+    };
+
+    let mut rotated = fit_output(rotated, empty_color, (width, height), options);
+    options
+        .palette
+        .constrain(image, empty_color, rotated.pixels_mut());
+
+    Ok(rotated)
+}
+
+/// Transform a sprite with an affine matrix, allowing shearing and stretching with the same pixel-art-friendly behaviour as [`rotsprite`].
+///
+/// The matrix maps positions in the source to positions in the output, both in pixels.
+/// The output is sized to the bounding box of the transformed sprite, where it's placed relative to the transformed source origin is returned by [`RotationResult::offset`].
+pub fn transform<P>(
+    buf: &[P],
+    empty_color: &P,
+    width: usize,
+    matrix: Affine,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+{
+    transform_with_options(buf, empty_color, width, matrix, &RotspriteOptions::new())
+}
+
+/// Transform a sprite like [`transform`] but with custom options.
+///
+/// [`RotspriteOptions::offset_search`] and [`RotspriteOptions::scale`] are ignored, the scale can be added to the matrix instead.
+pub fn transform_with_options<P, S, U, D, C>(
     buf: &[P],
     empty_color: &P,
     width: usize,
+    matrix: Affine,
+    options: &RotspriteOptions<S, U, D, C>,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+    S: Similarity<P>,
+    U: Upscaler<P>,
+    D: Downsampler<P>,
+    C: ColorConstraint<P>,
+{
+    let image = SubImage::new(buf, width)?;
+    let height = image.height();
+
+    if matrix.inverse().is_none() {
+        return Err(Error::InvalidTransform);
+    }
+    check_factor::<P, S, U, D, C>(options)?;
+    check_canvas_size::<P, S, U, D, C>(options)?;
+    check_sizes((width, height), matrix, options)?;
+
+    let (scaled_width, scaled_height, scaled) = upscale(&image, empty_color, options);
+
+    // Sample the upscaled image, which is smaller in the output
+    let factor = options.upscale.factor();
+    let fscale = factor as f64;
+    let transformed = rotate::transform(
+        &SubImage::packed(&scaled, scaled_width, scaled_height),
+        empty_color,
+        Affine::scale(1.0 / fscale, 1.0 / fscale).then(matrix),
+        factor,
+        &options.downsample,
+    )
+    .ok_or(Error::InvalidTransform)?;
+
+    let rotation = transformed.rotation();
+    let transformed = from_upscaled(transformed, &image, empty_color, rotation, options);
+
+    let mut transformed = fit_output(transformed, empty_color, (width, height), options);
+
+    // Rounding through the upscaled sprite can make nearly singular matrices singular
+    if transformed.matrix().inverse().is_none() {
+        return Err(Error::InvalidTransform);
+    }
+    options
+        .palette
+        .constrain(&image, empty_color, transformed.pixels_mut());
+
+    Ok(transformed)
+}
+
+/// Fit the rotated sprite on the requested canvas and remove the empty borders when requested.
+fn fit_output<P, S, U, D, C>(
+    rotated: RotationResult<P>,
+    empty_color: &P,
+    (width, height): (usize, usize),
+    options: &RotspriteOptions<S, U, D, C>,
+) -> RotationResult<P>
+where
+    P: Eq + Clone,
+{
+    let pivot = options.pivot_or_center(width, height);
+    let rotated = match options
+        .output_size
+        .canvas((width, height), pivot, rotated.pivot())
+    {
+        Some((size, position)) => crop_result(rotated, empty_color, position, size),
+        None => rotated,
+    };
+
+    if options.trim {
+        trim_result(rotated, empty_color)
+    } else {
+        rotated
+    }
+}
+
+/// Remove all fully empty rows and columns at the borders of a sprite.
+///
+/// The upper left corner of the remaining area in the source is returned by [`RotationResult::offset`].
+/// When all pixels are empty the result has a size of `(0, 0)`.
+pub fn trim<P>(buf: &[P], empty_color: &P, width: usize) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+{
+    let height = SubImage::new(buf, width)?.height();
+
+    let pivot = (width as f64 / 2.0, height as f64 / 2.0);
+    let source = RotationResult::new((width, height, buf.to_vec()), 0.0, Affine::IDENTITY, pivot);
+
+    Ok(trim_result(source, empty_color))
+}
+
+/// Check that a buffer of `width * height` pixels can be allocated.
+fn check_pixels<P>(width: usize, height: usize, max_pixels: Option<usize>) -> Result<(), Error> {
+    let pixels = width.checked_mul(height).ok_or(Error::TooLarge)?;
+    let bytes = pixels
+        .checked_mul(std::mem::size_of::<P>())
+        .ok_or(Error::TooLarge)?;
+
+    if bytes > isize::MAX as usize || max_pixels.is_some_and(|max_pixels| pixels > max_pixels) {
+        return Err(Error::TooLarge);
+    }
+
+    Ok(())
+}
+
+/// Check that the upscaler of the options has a factor the upscaled sprite can be sampled with.
+fn check_factor<P, S, U, D, C>(options: &RotspriteOptions<S, U, D, C>) -> Result<(), Error>
+where
+    U: Upscaler<P>,
+{
+    if options.upscale.factor() == 0 {
+        return Err(Error::ZeroFactor);
+    }
+
+    Ok(())
+}
+
+/// Check the size of the custom canvas before it's allocated.
+fn check_canvas_size<P, S, U, D, C>(options: &RotspriteOptions<S, U, D, C>) -> Result<(), Error> {
+    match options.output_size {
+        OutputSize::Custom(width, height) => check_pixels::<P>(width, height, options.max_pixels),
+        _ => Ok(()),
+    }
+}
+
+/// Check the size of the upscaled image and of the transformed output before they're allocated.
+fn check_sizes<P, S, U, D, C>(
+    (width, height): (usize, usize),
+    matrix: Affine,
+    options: &RotspriteOptions<S, U, D, C>,
+) -> Result<(), Error>
+where
+    U: Upscaler<P>,
+{
+    let factor = options.upscale.factor();
+    check_pixels::<P>(
+        width.checked_mul(factor).ok_or(Error::TooLarge)?,
+        height.checked_mul(factor).ok_or(Error::TooLarge)?,
+        options.max_pixels,
+    )?;
+
+    // Bounding box of the transformed sprite, with a margin for rounding to whole pixels
+    let corners = [
+        (0.0, 0.0),
+        (width as f64, 0.0),
+        (0.0, height as f64),
+        (width as f64, height as f64),
+    ]
+    .map(|corner| matrix.apply(corner));
+    let extent = |axis: fn(&(f64, f64)) -> f64| {
+        let (min, max) = corners
+            .iter()
+            .map(axis)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                (min.min(value), max.max(value))
+            });
+        let size = (max - min).ceil() + 2.0;
+
+        // Casting saturates, so values that don't fit are always rejected
+        if size.is_finite() {
+            Ok(size as usize)
+        } else {
+            Err(Error::TooLarge)
+        }
+    };
+
+    check_pixels::<P>(
+        extent(|(x, _)| *x)?,
+        extent(|(_, y)| *y)?,
+        options.max_pixels,
+    )
+}
+
+/// Validate the scale, it must be large enough for the matrix mapping the upscaled sprite to be inverted.
+///
+/// Scales too large to allocate are rejected later by [`check_sizes`].
+fn check_scale(scale: f64, factor: usize) -> Result<(), Error> {
+    let downscaled = scale / factor as f64;
+    if !(scale > 0.0 && scale.is_finite() && downscaled * downscaled >= f64::MIN_POSITIVE) {
+        return Err(Error::InvalidScale);
+    }
+
+    Ok(())
+}
+
+/// Validate the rotation in degrees and wrap it to the range `0.0..360.0`.
+fn normalize_rotation(rotation: f64) -> Result<f64, Error> {
+    if !rotation.is_finite() {
+        return Err(Error::InvalidAngle);
+    }
+
+    // Tiny negative angles wrap to exactly a whole turn
+    let rotation = rotation.rem_euclid(360.0);
+    if rotation == 360.0 {
+        return Ok(0.0);
+    }
+
+    Ok(rotation)
+}
+
+/// Remove the fully empty borders of a result, keeping the mapping to the source intact.
+fn trim_result<P>(result: RotationResult<P>, empty_color: &P) -> RotationResult<P>
+where
+    P: Eq + Clone,
+{
+    let (x, y, trimmed_width, trimmed_height) = trim_bounds(
+        result.pixels(),
+        empty_color,
+        result.width(),
+        result.height(),
+    )
+    .unwrap_or((0, 0, 0, 0));
+
+    crop_result(
+        result,
+        empty_color,
+        (x as isize, y as isize),
+        (trimmed_width, trimmed_height),
+    )
+}
+
+/// Copy an area of a result, keeping the mapping to the source intact.
+fn crop_result<P>(
+    result: RotationResult<P>,
+    empty_color: &P,
+    (x, y): (isize, isize),
+    (crop_width, crop_height): (usize, usize),
+) -> RotationResult<P>
+where
+    P: Clone,
+{
+    let cropped = crop(
+        result.pixels(),
+        empty_color,
+        result.width(),
+        result.height(),
+        (x, y),
+        (crop_width, crop_height),
+    );
+
+    let (pivot_x, pivot_y) = result.pivot();
+    let matrix = result.matrix().then(cropped.matrix());
+    RotationResult::new(
+        (crop_width, crop_height, cropped.into_pixels()),
+        result.rotation(),
+        matrix,
+        (pivot_x - x as f64, pivot_y - y as f64),
+    )
+}
+
+/// Rotate a sprite onto a canvas fitting the whole rotated sprite.
+fn rotsprite_expanded<P, S, U, D, C, I>(
+    image: &I,
+    empty_color: &P,
     rotation: f64,
-) -> Result<(usize, usize, Vec<P>), Error>
+    options: &RotspriteOptions<S, U, D, C>,
+) -> RotationResult<P>
 where
     P: Eq + Clone,
+    S: Similarity<P>,
+    U: Upscaler<P>,
+    D: Downsampler<P>,
+    I: ImageView<P>,
 {
-    // If there's no rotation we don't have to do anything
-    if rotation == 0.0 {
-        return Ok((width, buf.len() / width, buf.to_vec()));
+    let rotated = rotate_upscaled(image, empty_color, rotation, options);
+
+    from_upscaled(rotated, image, empty_color, rotation, options)
+}
+
+/// Upscale and rotate the mirrored sprite, positions are still mapped from the upscaled sprite.
+fn rotate_upscaled<P, S, U, D, C, I>(
+    image: &I,
+    empty_color: &P,
+    rotation: f64,
+    options: &RotspriteOptions<S, U, D, C>,
+) -> RotationResult<P>
+where
+    P: Eq + Clone,
+    S: Similarity<P>,
+    U: Upscaler<P>,
+    D: Downsampler<P>,
+    I: ImageView<P>,
+{
+    let (scaled_width, scaled_height, scaled) = upscale(image, empty_color, options);
+    let scaled = SubImage::packed(&scaled, scaled_width, scaled_height);
+
+    // Rotate the image, optionally searching for the sub-pixel offset with the fewest artifacts
+    let factor = options.upscale.factor();
+    if options.offset_search > 1 && rotation % 90.0 != 0.0 {
+        // The nearest-neighbour reference of the search samples the mirrored source
+        let (reversed_rows, half_turn) = options.flip.split();
+        let source = RowOrder::new(image, reversed_rows);
+        if half_turn {
+            let (width, height, turned) = rotate180(&source);
+            let source = SubImage::packed(&turned, width, height);
+            rotate_with_offset_search(&source, &scaled, empty_color, rotation, factor, options)
+        } else {
+            rotate_with_offset_search(&source, &scaled, empty_color, rotation, factor, options)
+        }
+    } else {
+        rotate_scaled_with_offset(
+            &scaled,
+            empty_color,
+            rotation,
+            options.scale,
+            factor,
+            (0.0, 0.0),
+            &options.downsample,
+        )
     }
+}
 
-    let len = buf.len();
-    if len % width != 0 {
-        return Err(Error::ImageSizeMismatch);
+/// Upscale the mirrored image with the upscaler of the options.
+fn upscale<P, S, U, D, C, I>(
+    image: &I,
+    empty_color: &P,
+    options: &RotspriteOptions<S, U, D, C>,
+) -> (usize, usize, Vec<P>)
+where
+    S: Similarity<P>,
+    U: Upscaler<P>,
+    D: Downsampler<P>,
+    I: ImageView<P>,
+{
+    let (width, height) = image.size();
+    let factor = options.upscale.factor();
+    // Mirror top and bottom by reading the rows in reverse order and turn the packed result in place,
+    // so the sprite isn't copied before upscaling
+    let (reversed_rows, half_turn) = options.flip.split();
+    let mut scaled = options.upscale.upscale(
+        &RowOrder::new(image, reversed_rows),
+        &options.similarity,
+        options.border,
+        empty_color,
+    );
+    debug_assert_eq!(
+        scaled.len(),
+        width * factor * height * factor,
+        "upscaler returned a buffer with the wrong size"
+    );
+    if half_turn {
+        scaled.reverse();
     }
-    let height = len / width;
 
-    // Upscale the image using the scale2x algorithm
-    // 2x
-    let (scaled_width, scaled_height, scaled) = scale2x(buf, width, height);
-    // 4x
-    let (scaled_width, scaled_height, scaled) = scale2x(&scaled, scaled_width, scaled_height);
-    // 8x
-    let (scaled_width, scaled_height, scaled) = scale2x(&scaled, scaled_width, scaled_height);
+    (width * factor, height * factor, scaled)
+}
+
+/// Map positions from the source instead of the mirrored upscaled source, and draw back details when requested.
+fn from_upscaled<P, S, U, D, C, I>(
+    rotated: RotationResult<P>,
+    image: &I,
+    empty_color: &P,
+    rotation: f64,
+    options: &RotspriteOptions<S, U, D, C>,
+) -> RotationResult<P>
+where
+    P: Eq + Clone,
+    U: Upscaler<P>,
+    D: Downsampler<P>,
+    I: ImageView<P>,
+{
+    let (width, height) = image.size();
+    let factor = options.upscale.factor() as f64;
+    let matrix = options
+        .flip
+        .matrix(width, height)
+        .then(Affine::scale(factor, factor))
+        .then(rotated.matrix());
+    let pivot = matrix.apply(options.pivot_or_center(width, height));
+    let mut rotated = RotationResult::new(
+        (rotated.width(), rotated.height(), rotated.into_pixels()),
+        rotation,
+        matrix,
+        pivot,
+    );
+
+    // Draw back small details that got lost
+    if options.restore_details {
+        restore_details(image, empty_color, &mut rotated, |x, y| {
+            matrix.apply((x, y))
+        });
+    }
+
+    rotated
+}
+
+/// Rotate the upscaled sprite with a grid of sub-pixel offsets and keep the one with the fewest artifacts.
+///
+/// Artifacts are counted as the pixels that differ from a nearest-neighbour rotation of the source, sampled at the center of each output pixel.
+fn rotate_with_offset_search<P, S, U, D, C, I>(
+    image: &I,
+    scaled: &SubImage<'_, P>,
+    empty_color: &P,
+    rotation: f64,
+    factor: usize,
+    options: &RotspriteOptions<S, U, D, C>,
+) -> RotationResult<P>
+where
+    P: Eq + Clone,
+    D: Downsampler<P>,
+    I: ImageView<P>,
+{
+    let steps = options.offset_search;
 
-    // Rotate the image
-    let rotated = rotate(
-        &scaled,
+    // Offset in the source that maps to the center of a destination pixel
+    let scale = options.scale;
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let center = (0.5 * (cos + sin) / scale, 0.5 * (cos - sin) / scale);
+    let reference = rotate_scaled_with_offset(
+        image,
         empty_color,
-        scaled_width,
-        scaled_height,
         rotation,
-        8,
+        scale,
+        1,
+        center,
+        &Downsample::Nearest,
     );
 
-    Ok(rotated)
+    let step_size = factor as f64 / steps as f64;
+    let mut best = None;
+    let mut best_artifacts = usize::MAX;
+    for y in 0..steps {
+        for x in 0..steps {
+            let rotated = rotate_scaled_with_offset(
+                scaled,
+                empty_color,
+                rotation,
+                scale,
+                factor,
+                (x as f64 * step_size, y as f64 * step_size),
+                &options.downsample,
+            );
+
+            let artifacts = rotated
+                .pixels()
+                .iter()
+                .zip(reference.pixels().iter())
+                .filter(|(pixel, reference)| pixel != reference)
+                .count();
+
+            // Only replace on strict improvements so the unshifted rotation wins ties
+            if artifacts < best_artifacts {
+                best = Some(rotated);
+                best_artifacts = artifacts;
+            }
+        }
+    }
+
+    // There's always at least a single step
+    best.expect("no offsets tried")
 }
 
 #[cfg(test)]
@@ -116,13 +736,13 @@ mod tests {
     #[test]
     fn rotation_size() -> Result<(), Error> {
         let buf = [1, 2, 3, 4, 5, 6].to_vec();
-        let (w, h, _) = rotsprite(&buf, &0, 3, 45.0)?;
+        let (w, h, _) = rotsprite(&buf, &0, 3, 45.0)?.into();
         assert_eq!(w, 4);
         assert_eq!(h, 4);
-        let (w, h, _) = rotsprite(&buf, &0, 3, 90.0)?;
+        let (w, h, _) = rotsprite(&buf, &0, 3, 90.0)?.into();
         assert_eq!(w, 2);
         assert_eq!(h, 3);
-        let (w, h, _) = rotsprite(&buf, &0, 3, 180.0)?;
+        let (w, h, _) = rotsprite(&buf, &0, 3, 180.0)?.into();
         assert_eq!(w, 3);
         assert_eq!(h, 2);
 
@@ -132,7 +752,7 @@ mod tests {
     #[test]
     fn rotation_test() -> Result<(), Error> {
         let buf = [1, 2, 3, 4, 5, 6].to_vec();
-        let (w, h, new) = rotsprite(&buf, &0, 3, 90.0)?;
+        let (w, h, new) = rotsprite(&buf, &0, 3, 90.0)?.into();
         assert_eq!(w, 2);
         assert_eq!(h, 3);
         assert_eq!(new, [4, 1, 5, 2, 6, 3]);
@@ -143,7 +763,7 @@ mod tests {
     #[test]
     fn no_rotation_test() -> Result<(), Error> {
         let buf = [1, 0, 0, 1, 1, 0].to_vec();
-        let (w, h, new) = rotsprite(&buf, &-1, 2, 0.0)?;
+        let (w, h, new) = rotsprite(&buf, &-1, 2, 0.0)?.into();
         assert_eq!(w, 2);
         assert_eq!(h, 3);
         assert_eq!(buf, new);
@@ -151,6 +771,787 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn upscale_levels() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6].to_vec();
+        for upscale in [
+            Upscale::X1,
+            Upscale::X2,
+            Upscale::X3,
+            Upscale::X4,
+            Upscale::X8,
+            Upscale::X9,
+            Upscale::X16,
+        ] {
+            let options = RotspriteOptions::new().with_upscale(upscale);
+            let (w, h, new) = rotsprite_with_options(&buf, &0, 3, 90.0, &options)?.into();
+            assert_eq!((w, h), (2, 3));
+            assert_eq!(new, [4, 1, 5, 2, 6, 3]);
+        }
+
+        assert_eq!(
+            rotsprite(&buf, &0, 3, 45.0)?,
+            rotsprite_with_options(&buf, &0, 3, 45.0, &RotspriteOptions::new())?
+        );
+
+        // Odd upscale levels have the same rotated size
+        let options = RotspriteOptions::new().with_upscale(Upscale::X9);
+        let rotated = rotsprite_with_options(&buf, &0, 3, 45.0, &options)?;
+        assert_eq!(rotated.size(), rotsprite(&buf, &0, 3, 45.0)?.size());
+        assert_eq!(Upscale::X9.factor(), 9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn upscaler_option() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6].to_vec();
+        let expected = rotsprite(&buf, &0, 3, 45.0)?;
+
+        // The default upscaler is the same as repeating scale2x
+        let options = RotspriteOptions::new().with_upscale(Repeat(Scale2x, 3));
+        assert_eq!(
+            rotsprite_with_options(&buf, &0, 3, 45.0, &options)?,
+            expected
+        );
+
+        let options = RotspriteOptions::new().with_upscale(Repeat(Eagle, 2));
+        let rotated = rotsprite_with_options(&buf, &0, 3, 45.0, &options)?;
+        assert_eq!(rotated.size(), expected.size());
+
+        let options = RotspriteOptions::new().with_upscale(Epx);
+        let rotated = rotsprite_with_options(&buf, &0, 3, 90.0, &options)?;
+        assert_eq!(rotated.pixels(), [4, 1, 5, 2, 6, 3]);
+
+        // Upscalers needing color channels
+        let buf = [1_u32, 2, 3, 4, 5, 6];
+        let options = RotspriteOptions::new()
+            .with_upscale(Repeat(Xbr::X4, 2))
+            .with_similarity(RgbaDistance::new(0));
+        let rotated = rotsprite_with_options(&buf, &0, 3, 45.0, &options)?;
+        assert_eq!(rotated.size(), expected.size());
+
+        // Upscalers that shrink the sprite to nothing can't be sampled
+        struct Nothing;
+        impl<P> Upscaler<P> for Nothing {
+            fn factor(&self) -> usize {
+                0
+            }
+
+            fn upscale<S, I>(&self, _: &I, _: &S, _: Border, _: &P) -> Vec<P> {
+                Vec::new()
+            }
+        }
+        let options = RotspriteOptions::new().with_upscale(Nothing);
+        assert_eq!(
+            rotsprite_with_options(&buf, &0, 3, 45.0, &options).unwrap_err(),
+            Error::ZeroFactor
+        );
+        assert_eq!(
+            transform_with_options(&buf, &0, 3, Affine::rotation(45.0), &options).unwrap_err(),
+            Error::ZeroFactor
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn similarity_option() -> Result<(), Error> {
+        let buf = [
+            0xFF_00_00_00_u32,
+            0xFF_00_00_00,
+            0xFF_FF_FF_FF,
+            0xFF_00_00_00,
+            0xFF_FF_FF_FF,
+            0xFF_FF_FF_FE,
+        ]
+        .to_vec();
+
+        // A threshold of zero is the same as the default exact comparison
+        assert_eq!(
+            rotsprite(&buf, &0, 3, 30.0)?,
+            rotsprite_with_options(
+                &buf,
+                &0,
+                3,
+                30.0,
+                &RotspriteOptions::new().with_similarity(RgbaDistance::new(0))
+            )?
+        );
+
+        // Closures can also be used
+        let options = RotspriteOptions::new().with_similarity(|a: &u32, b: &u32| a == b);
+        assert_eq!(
+            rotsprite(&buf, &0, 3, 30.0)?,
+            rotsprite_with_options(&buf, &0, 3, 30.0, &options)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn offset_search() -> Result<(), Error> {
+        let buf = [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0].to_vec();
+
+        // Disabled searches are the same as the default
+        for offset_search in [0, 1] {
+            let options = RotspriteOptions::new().with_offset_search(offset_search);
+            assert_eq!(
+                rotsprite(&buf, &0, 4, 30.0)?,
+                rotsprite_with_options(&buf, &0, 4, 30.0, &options)?
+            );
+        }
+
+        // Searching never changes the size or the result of axis aligned rotations
+        let options = RotspriteOptions::new().with_offset_search(4);
+        let searched = rotsprite_with_options(&buf, &0, 4, 30.0, &options)?;
+        let unshifted = rotsprite(&buf, &0, 4, 30.0)?;
+        assert_eq!(searched.size(), unshifted.size());
+
+        // The search picks another offset, with at most as many artifacts as the unshifted rotation
+        let (sin, cos) = 30.0_f64.to_radians().sin_cos();
+        let center = (0.5 * (cos + sin), 0.5 * (cos - sin));
+        let reference = rotate_with_offset(&buf, &0, 4, 4, 30.0, 1, center, &Downsample::Nearest)?;
+        assert_eq!(reference.size(), unshifted.size());
+        let artifacts = |rotated: &RotationResult<i32>| {
+            rotated
+                .pixels()
+                .iter()
+                .zip(reference.pixels())
+                .filter(|(pixel, reference)| pixel != reference)
+                .count()
+        };
+        assert_ne!(searched.pixels(), unshifted.pixels());
+        assert!(artifacts(&searched) <= artifacts(&unshifted));
+
+        assert_eq!(
+            rotsprite(&buf, &0, 4, 90.0)?,
+            rotsprite_with_options(&buf, &0, 4, 90.0, &options)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn downsample_option() -> Result<(), Error> {
+        let buf = [1, 1, 1, 1, 2, 1, 1, 1, 1].to_vec();
+
+        let options =
+            RotspriteOptions::new().with_downsample(Downsample::Majority(TieBreak::NonEmpty));
+        let (w, h, new) = rotsprite_with_options(&buf, &0, 3, 90.0, &options)?.into();
+        assert_eq!((w, h), (3, 3));
+        assert_eq!(new, buf);
+
+        // Preferring non-empty colors on ties never shrinks the silhouette
+        let (w, h, non_empty) = rotsprite_with_options(&buf, &0, 3, 45.0, &options)?.into();
+        assert_eq!((w, h), rotsprite(&buf, &0, 3, 45.0)?.size());
+        let options = options.with_downsample(Downsample::Majority(TieBreak::Center));
+        let (_, _, center) = rotsprite_with_options(&buf, &0, 3, 45.0, &options)?.into();
+        assert!(
+            non_empty.iter().filter(|pixel| **pixel != 0).count()
+                >= center.iter().filter(|pixel| **pixel != 0).count()
+        );
+
+        let options = options.with_downsample(Downsample::Center);
+        let (w, h, new) = rotsprite_with_options(&buf, &0, 3, 90.0, &options)?.into();
+        assert_eq!((w, h), (3, 3));
+        assert_eq!(new, buf);
+
+        // Averaging introduces half transparent pixels at the edges
+        let buf = [0xFF_FF_00_00_u32; 9];
+        let options = RotspriteOptions::new().with_downsample(AlphaWeighted);
+        let rotated = rotsprite_with_options(&buf, &0, 3, 30.0, &options)?;
+        assert!(rotated
+            .pixels()
+            .iter()
+            .any(|pixel| (1..0xFF).contains(&(pixel >> 24))));
+        assert!(rotated
+            .pixels()
+            .iter()
+            .all(|pixel| *pixel == 0 || pixel & 0xFF_FF_FF == 0xFF_00_00));
+
+        Ok(())
+    }
+
+    #[test]
+    fn anti_aliasing_option() -> Result<(), Error> {
+        let (red, blue) = ([0xFF, 0, 0, 0xFF], [0, 0, 0xFF, 0xFF]);
+        let buf = [red, red, blue, red, blue, blue, blue, blue, blue];
+        let empty = [0; 4];
+
+        // By default only colors of the source are used
+        let hard = rotsprite(&buf, &empty, 3, 30.0)?;
+        assert!(hard
+            .pixels()
+            .iter()
+            .all(|pixel| [red, blue, empty].contains(pixel)));
+
+        let options = RotspriteOptions::new().with_anti_aliasing();
+        let smooth = rotsprite_with_options(&buf, &empty, 3, 30.0, &options)?;
+        assert_eq!(smooth.size(), hard.size());
+        assert!(smooth
+            .pixels()
+            .iter()
+            .any(|pixel| ![red, blue, empty].contains(pixel)));
+        assert_eq!(
+            smooth.pixels(),
+            rotsprite_with_options(
+                &buf,
+                &empty,
+                3,
+                30.0,
+                &RotspriteOptions::new().with_downsample(AlphaWeighted)
+            )?
+            .pixels()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn palette_option() -> Result<(), Error> {
+        fn only_source_colors<P>(buf: &[P], empty: P) -> Result<(), Error>
+        where
+            P: RgbaPixel + Eq + std::hash::Hash + Clone,
+        {
+            let options = RotspriteOptions::new()
+                .with_anti_aliasing()
+                .with_palette(SourcePalette(EuclideanDistance));
+            let rotated = rotsprite_with_options(buf, &empty, 3, 30.0, &options)?;
+            assert!(rotated
+                .pixels()
+                .iter()
+                .all(|pixel| *pixel == empty || buf.contains(pixel)));
+
+            Ok(())
+        }
+
+        let (red, blue) = ([0xFF, 0, 0, 0xFF], [0, 0, 0xFF, 0xFF]);
+        let buf = [red, red, blue, red, blue, blue, blue, blue, blue];
+        only_source_colors(&buf, [0; 4])?;
+        only_source_colors(&buf.map(u32::from_rgba), 0)?;
+        #[cfg(feature = "image")]
+        only_source_colors(&buf.map(::image::Rgba), ::image::Rgba([0; 4]))?;
+
+        // Snapping a palette-exact rotation doesn't change anything
+        let options = RotspriteOptions::new().with_palette(SourcePalette(EuclideanDistance));
+        assert_eq!(
+            rotsprite_with_options(&buf, &[0; 4], 3, 30.0, &options)?.pixels(),
+            rotsprite(&buf, &[0; 4], 3, 30.0)?.pixels()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn restore_details_option() -> Result<(), Error> {
+        let buf = [1, 1, 1, 1, 2, 1, 1, 1, 1].to_vec();
+
+        // The single pixel in the center is lost when taking the majority
+        let options =
+            RotspriteOptions::new().with_downsample(Downsample::Majority(TieBreak::Center));
+        let (_, _, lost) = rotsprite_with_options(&buf, &0, 3, 45.0, &options)?.into();
+        assert!(!lost.contains(&2));
+
+        let options = options.with_restore_details(true);
+        let (w, h, restored) = rotsprite_with_options(&buf, &0, 3, 45.0, &options)?.into();
+        assert_eq!(restored.iter().filter(|pixel| **pixel == 2).count(), 1);
+        assert_eq!(restored[w * (h / 2) + w / 2], 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pivot_position() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6].to_vec();
+
+        // The center of the sprite stays in the center of the rotated sprite
+        for rotation in [0.0, 90.0, 180.0, 270.0] {
+            let rotated = rotsprite(&buf, &0, 3, rotation)?;
+            let ((w, h), (x, y)) = (rotated.size(), rotated.pivot());
+            assert!((x - w as f64 / 2.0).abs() <= 0.5, "{rotation}: {x} {w}");
+            assert!((y - h as f64 / 2.0).abs() <= 0.5, "{rotation}: {y} {h}");
+        }
+
+        // Nearest-neighbour shows the lower right of each pixel, moving the pivot up and left
+        let rotated = rotsprite(&buf, &0, 3, 45.0)?;
+        let ((w, h), (x, y)) = (rotated.size(), rotated.pivot());
+        assert!(x < w as f64 / 2.0 && x > w as f64 / 2.0 - 1.0);
+        assert!(y < h as f64 / 2.0 && y > h as f64 / 2.0 - 1.0);
+
+        // Corners of the sprite map to corners of the rotated sprite
+        let options = RotspriteOptions::new().with_pivot((0.0, 0.0));
+        let (x, y) = rotsprite_with_options(&buf, &0, 3, 90.0, &options)?.pivot();
+        assert!((x - 2.0).abs() < 1e-9 && y.abs() < 1e-9);
+        let options = RotspriteOptions::new().with_pivot((3.0, 2.0));
+        let (x, y) = rotsprite_with_options(&buf, &0, 3, 180.0, &options)?.pivot();
+        assert!(x.abs() < 1e-9 && y.abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_result() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6].to_vec();
+
+        let rotated = rotsprite(&buf, &0, 3, -90.0)?;
+        assert_eq!(rotated.rotation(), 270.0);
+        assert_eq!(rotated.size(), (2, 3));
+        assert_eq!(rotated.pixels(), [3, 6, 2, 5, 1, 4]);
+
+        // The source origin ends up in the lower left corner
+        let (x, y) = rotated.offset();
+        assert!(x.abs() < 1e-9 && (y + 3.0).abs() < 1e-9);
+
+        // The matrix maps every source pixel to the same rotated pixel
+        for (i, pixel) in buf.iter().enumerate() {
+            let (x, y) = rotated
+                .matrix()
+                .apply(((i % 3) as f64 + 0.5, (i / 3) as f64 + 0.5));
+            assert_eq!(&rotated.pixels()[y as usize * 2 + x as usize], pixel);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_size() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6, 7, 8].to_vec();
+
+        // Rotating around the center keeps the sprite in place
+        let options = RotspriteOptions::new().with_output_size(OutputSize::KeepSource);
+        let rotated = rotsprite_with_options(&buf, &0, 4, 90.0, &options)?;
+        assert_eq!(rotated.size(), (4, 2));
+        assert_eq!(rotated.pivot(), (2.0, 1.0));
+        assert_eq!(rotated.pixels(), [0, 6, 2, 0, 0, 7, 3, 0]);
+
+        let rotated = rotsprite_with_options(&buf, &0, 4, 30.0, &options)?;
+        assert_eq!(rotated.size(), (4, 2));
+
+        // Custom canvases center the pivot
+        let options = RotspriteOptions::new()
+            .with_output_size(OutputSize::Custom(2, 2))
+            .with_pivot((1.0, 1.0));
+        let rotated = rotsprite_with_options(&buf, &0, 4, 0.0, &options)?;
+        assert_eq!(rotated.pixels(), [1, 2, 5, 6]);
+        assert_eq!(rotated.pivot(), (1.0, 1.0));
+        assert_eq!(rotated.mapping().map((1.5, 1.5)), (1.5, 1.5));
+
+        // Distant pivots leave the canvas empty
+        let options = RotspriteOptions::new()
+            .with_output_size(OutputSize::Custom(2, 2))
+            .with_pivot((-1e300, 0.0));
+        for rotation in [0.0, 30.0] {
+            let rotated = rotsprite_with_options(&[1, 2, 3, 4, 5, 6], &0, 3, rotation, &options)?;
+            assert_eq!(rotated.pixels(), [0, 0, 0, 0]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn trim_test() -> Result<(), Error> {
+        #[rustfmt::skip]
+        let buf = [
+            0, 0, 0, 0,
+            0, 1, 2, 0,
+            0, 0, 3, 0,
+        ];
+        let trimmed = trim(&buf, &0, 4)?;
+        assert_eq!(trimmed.size(), (2, 2));
+        assert_eq!(trimmed.pixels(), [1, 2, 0, 3]);
+        assert_eq!(trimmed.offset(), (1.0, 1.0));
+
+        assert_eq!(trim(&[0, 0, 0, 0], &0, 2)?.size(), (0, 0));
+
+        // Trimming after drawing on the canvas
+        let buf = [1, 2, 3, 4, 5, 6, 7, 8];
+        let options = RotspriteOptions::new()
+            .with_output_size(OutputSize::KeepSource)
+            .with_trim(true);
+        let rotated = rotsprite_with_options(&buf, &0, 4, 90.0, &options)?;
+        assert_eq!(rotated.pixels(), [6, 2, 7, 3]);
+        assert_eq!(rotated.pivot(), (1.0, 1.0));
+
+        // The mapping still points to the same pixels
+        let options = RotspriteOptions::new();
+        let expanded = rotsprite_with_options(&buf, &0, 4, 30.0, &options)?;
+        let rotated = rotsprite_with_options(&buf, &0, 4, 30.0, &options.with_trim(true))?;
+        let (x, y) = rotated.mapping().map((0.5, 0.5));
+        let (expanded_x, expanded_y) = expanded.mapping().map((0.5, 0.5));
+        assert_eq!(
+            rotated.pixels()[y as usize * rotated.width() + x as usize],
+            expanded.pixels()[expanded_y as usize * expanded.width() + expanded_x as usize]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_scaled() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6];
+
+        // Only scaling duplicates the pixels
+        let scaled = rotsprite_scaled(&buf, &0, 3, 0.0, 2.0)?;
+        assert_eq!(scaled.size(), (6, 4));
+        assert_eq!(scaled.pixels()[..6], [1, 1, 2, 2, 3, 3]);
+        assert_eq!(scaled.pivot(), (3.0, 2.0));
+
+        let rotated = rotsprite(&buf, &0, 3, 30.0)?;
+        let scaled = rotsprite_scaled(&buf, &0, 3, 30.0, 1.5)?;
+        assert!(scaled.width() > rotated.width() && scaled.height() > rotated.height());
+
+        // The mapping follows the scale
+        let (x, y) = scaled.mapping().map((1.5, 0.5));
+        assert_eq!(scaled.pixels()[y as usize * scaled.width() + x as usize], 2);
+
+        // A scale of 1.0 doesn't change anything
+        assert_eq!(rotsprite_scaled(&buf, &0, 3, 30.0, 1.0)?, rotated);
+
+        // Scales too small to map the output back to the source are rejected
+        for scale in [0.0, -1.0, 1e-200] {
+            assert_eq!(
+                rotsprite_scaled(&buf, &0, 3, 30.0, scale).unwrap_err(),
+                Error::InvalidScale
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn transform_test() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6];
+
+        // Stretching horizontally
+        let stretched = transform(&buf, &0, 3, Affine::scale(2.0, 1.0))?;
+        assert_eq!(stretched.size(), (6, 2));
+        assert_eq!(stretched.pixels(), [1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6]);
+        assert_eq!(stretched.offset(), (0.0, 0.0));
+
+        // Shearing grows the bounds to the left
+        let sheared = transform(&buf, &0, 3, Affine([[1.0, -1.0, 0.0], [0.0, 1.0, 0.0]]))?;
+        assert_eq!(sheared.size(), (5, 2));
+        // Shearing isn't axis aligned, so the offset includes where nearest-neighbour samples each pixel
+        assert_eq!(sheared.offset(), (-1.625, 0.375));
+        assert!(buf.iter().all(|pixel| sheared.pixels().contains(pixel)));
+
+        // Rotating matches the pixels of the rotation
+        let rotated = transform(&buf, &0, 3, Affine::rotation(90.0))?;
+        assert_eq!(rotated.pixels(), [4, 1, 5, 2, 6, 3]);
+
+        assert_eq!(
+            transform(&buf, &0, 3, Affine::scale(0.0, 1.0)).unwrap_err(),
+            Error::InvalidTransform
+        );
+
+        // Nearly singular matrices that become singular through the upscaled sprite are rejected
+        let buf = (1..=12).collect::<Vec<_>>();
+        let matrix = Affine([[1.0, 1.0, 0.0], [1.0, 1.0 + f64::EPSILON, 0.0]]);
+        let options = RotspriteOptions::new().with_upscale(Upscale::X3);
+        assert_eq!(
+            transform_with_options(&buf, &0, 4, matrix, &options).unwrap_err(),
+            Error::InvalidTransform
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn flip_option() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6];
+
+        let options = RotspriteOptions::new().with_flip(Flip::Horizontal);
+        let flipped = rotsprite_with_options(&buf, &0, 3, 0.0, &options)?;
+        assert_eq!(flipped.pixels(), [3, 2, 1, 6, 5, 4]);
+        assert_eq!(flipped.mapping().map((0.5, 0.5)), (2.5, 0.5));
+
+        // Flipping and rotating is the same as rotating the flipped sprite
+        let options = RotspriteOptions::new().with_flip(Flip::Vertical);
+        for rotation in [90.0, 30.0] {
+            let rotated = rotsprite_with_options(&buf, &0, 3, rotation, &options)?;
+            let expected = rotsprite(&[4, 5, 6, 1, 2, 3], &0, 3, rotation)?;
+            assert_eq!(rotated.pixels(), expected.pixels());
+
+            // Positions stay in the source before mirroring
+            let (x, y) = rotated.mapping().map((0.5, 0.5));
+            assert_eq!(
+                rotated.pixels()[y as usize * rotated.width() + x as usize],
+                1
+            );
+        }
+
+        // Mirroring left and right turns the upscaled sprite instead of copying the source
+        for (flip, flipped) in [
+            (Flip::Horizontal, [3, 2, 1, 6, 5, 4]),
+            (Flip::Both, [6, 5, 4, 3, 2, 1]),
+        ] {
+            for offset_search in [1, 3] {
+                let options = RotspriteOptions::new()
+                    .with_flip(flip)
+                    .with_offset_search(offset_search);
+                let expected = RotspriteOptions::new().with_offset_search(offset_search);
+                assert_eq!(
+                    rotsprite_with_options(&buf, &0, 3, 30.0, &options)?.pixels(),
+                    rotsprite_with_options(&flipped, &0, 3, 30.0, &expected)?.pixels()
+                );
+            }
+
+            let matrix = Affine([[1.0, 0.5, 0.0], [0.0, 1.0, 0.0]]);
+            let options = RotspriteOptions::new().with_flip(flip);
+            assert_eq!(
+                transform_with_options(&buf, &0, 3, matrix, &options)?.pixels(),
+                transform(&flipped, &0, 3, matrix)?.pixels()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn border_option() -> Result<(), Error> {
+        let buf = [1; 16];
+        let filled =
+            |result: &RotationResult<i32>| result.pixels().iter().filter(|p| **p == 1).count();
+
+        let clamp = rotsprite(&buf, &0, 4, 30.0)?;
+        let options = RotspriteOptions::new().with_border(Border::Clamp);
+        assert_eq!(
+            rotsprite_with_options(&buf, &0, 4, 30.0, &options)?.pixels(),
+            clamp.pixels()
+        );
+
+        // The corners of the square are rounded off when its edges are next to the empty color
+        let options = RotspriteOptions::new().with_border(Border::Empty);
+        let empty = rotsprite_with_options(&buf, &0, 4, 30.0, &options)?;
+        assert!(filled(&empty) < filled(&clamp));
+
+        // A single color wraps around to itself
+        let options = RotspriteOptions::new().with_border(Border::Wrap);
+        assert_eq!(
+            rotsprite_with_options(&buf, &0, 4, 30.0, &options)?.pixels(),
+            clamp.pixels()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sub_image_rotation() -> Result<(), Error> {
+        #[rustfmt::skip]
+        let atlas = [
+            9, 9, 9, 9, 9, 9,
+            9, 1, 2, 3, 4, 9,
+            9, 5, 6, 7, 8, 9,
+            9, 9, 9, 9, 9, 9,
+        ];
+        let packed = [1, 2, 3, 4, 5, 6, 7, 8];
+        let image = SubImage::from_rect(&atlas, 6, (1, 1, 4, 2))?;
+
+        for rotation in [0.0, 30.0, 90.0, 200.0] {
+            assert_eq!(
+                rotsprite_view(&image, &0, rotation)?,
+                rotsprite(&packed, &0, 4, rotation)?
+            );
+        }
+
+        let options = RotspriteOptions::new()
+            .with_flip(Flip::Horizontal)
+            .with_border(Border::Wrap);
+        assert_eq!(
+            rotsprite_view_with_options(&image, &0, 45.0, &options)?,
+            rotsprite_with_options(&packed, &0, 4, 45.0, &options)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn image_view_rotation() -> Result<(), Error> {
+        // Image type of which the rows aren't stored next to each other
+        struct Rows(Vec<Vec<i32>>);
+
+        impl ImageView<i32> for Rows {
+            fn width(&self) -> usize {
+                self.0[0].len()
+            }
+
+            fn height(&self) -> usize {
+                self.0.len()
+            }
+
+            fn row(&self, y: usize) -> &[i32] {
+                &self.0[y]
+            }
+        }
+
+        let packed = [1, 2, 3, 4, 5, 6];
+        let rows = Rows(vec![vec![1, 2, 3], vec![4, 5, 6]]);
+        assert_eq!(
+            rotsprite_view(&rows, &0, 30.0)?,
+            rotsprite(&packed, &0, 3, 30.0)?
+        );
+
+        // Results can be rotated again without copying them
+        let rotated = rotsprite(&packed, &0, 3, 45.0)?;
+        assert_eq!(
+            rotsprite_view(&rotated, &0, 45.0)?.pixels(),
+            rotsprite(rotated.pixels(), &0, rotated.width(), 45.0)?.pixels()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn degenerate_sizes() -> Result<(), Error> {
+        assert_eq!(
+            rotsprite(&[0; 4], &0, 0, 30.0).unwrap_err(),
+            Error::ZeroWidth
+        );
+        assert_eq!(
+            rotsprite(&[0; 4], &0, 0, 0.0).unwrap_err(),
+            Error::ZeroWidth
+        );
+        assert_eq!(
+            rotsprite::<i32>(&[], &0, 2, 30.0).unwrap_err(),
+            Error::EmptyBuffer
+        );
+        assert_eq!(
+            rotsprite::<i32>(&[], &0, 2, 0.0).unwrap_err(),
+            Error::EmptyBuffer
+        );
+        assert_eq!(trim::<i32>(&[], &0, 0).unwrap_err(), Error::ZeroWidth);
+        assert_eq!(
+            transform::<i32>(&[], &0, 1, Affine::IDENTITY).unwrap_err(),
+            Error::EmptyBuffer
+        );
+
+        // Single pixel rows and columns
+        for (buf, width) in [(&[1][..], 1), (&[1, 2, 3], 3), (&[1, 2, 3], 1)] {
+            for rotation in [0.0, 30.0, 45.0, 90.0, 180.0] {
+                let rotated = rotsprite(buf, &0, width, rotation)?;
+                assert!(rotated.pixels().iter().any(|pixel| *pixel != 0));
+            }
+
+            for options in [
+                RotspriteOptions::new().with_border(Border::Empty),
+                RotspriteOptions::new().with_border(Border::Wrap),
+            ] {
+                rotsprite_with_options(buf, &0, width, 30.0, &options)?;
+            }
+            rotsprite_with_options(
+                buf,
+                &0,
+                width,
+                30.0,
+                &RotspriteOptions::new().with_upscale(Upscale::X9),
+            )?;
+            rotsprite_with_options(
+                buf,
+                &0,
+                width,
+                30.0,
+                &RotspriteOptions::new().with_upscale(Repeat(Eagle, 2)),
+            )?;
+            transform(
+                buf,
+                &0,
+                width,
+                Affine::rotation(30.0).then(Affine::scale(1.5, 0.5)),
+            )?;
+        }
+
+        // Trimming away everything leaves an empty result
+        let trimmed = trim(&[0], &0, 1)?;
+        assert_eq!(trimmed.size(), (0, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_angle() -> Result<(), Error> {
+        for rotation in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(
+                rotsprite(&[1; 4], &0, 2, rotation).unwrap_err(),
+                Error::InvalidAngle
+            );
+        }
+
+        // Nonsense matrices can't be inverted
+        for matrix in [
+            Affine::rotation(f64::NAN),
+            Affine::translation(f64::INFINITY, 0.0),
+        ] {
+            assert_eq!(
+                transform(&[1; 4], &0, 2, matrix).unwrap_err(),
+                Error::InvalidTransform
+            );
+        }
+
+        // Angles outside of a single turn are wrapped
+        let buf = [1, 2, 3, 4, 5, 6];
+        for (rotation, normalized) in [
+            (-90.0, 270.0),
+            (390.0, 30.0),
+            (360.0, 0.0),
+            (-720.0, 0.0),
+            (-1e-20, 0.0),
+        ] {
+            assert_eq!(normalize_rotation(rotation), Ok(normalized));
+            assert_eq!(
+                rotsprite(&buf, &0, 3, rotation)?.pixels(),
+                rotsprite(&buf, &0, 3, normalized)?.pixels()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn too_large() -> Result<(), Error> {
+        let buf = [1; 16];
+
+        // The upscaled image of 32x32 pixels doesn't fit
+        let options = RotspriteOptions::new().with_max_pixels(1000);
+        assert_eq!(
+            rotsprite_with_options(&buf, &0, 4, 30.0, &options).unwrap_err(),
+            Error::TooLarge
+        );
+        let options = RotspriteOptions::new().with_max_pixels(1024);
+        rotsprite_with_options(&buf, &0, 4, 30.0, &options)?;
+
+        // The limit is the same for every angle, even when nothing has to be upscaled
+        let options = RotspriteOptions::new().with_max_pixels(10);
+        for rotation in [0.0, 90.0, 30.0] {
+            assert_eq!(
+                rotsprite_with_options(&[1; 64], &0, 8, rotation, &options).unwrap_err(),
+                Error::TooLarge
+            );
+        }
+
+        // Sizes that can't be allocated are always rejected
+        assert_eq!(
+            rotsprite_scaled(&buf, &0, 4, 30.0, 1e200).unwrap_err(),
+            Error::TooLarge
+        );
+        assert_eq!(
+            transform(&buf, &0, 4, Affine::scale(1e20, 1e20)).unwrap_err(),
+            Error::TooLarge
+        );
+        let options = RotspriteOptions::new().with_output_size(OutputSize::Custom(usize::MAX, 2));
+        assert_eq!(
+            rotsprite_with_options(&buf, &0, 4, 0.0, &options).unwrap_err(),
+            Error::TooLarge
+        );
+        let options = RotspriteOptions::new().with_upscale(Repeat(Scale2x, 70));
+        assert_eq!(
+            rotsprite_with_options(&buf, &0, 4, 30.0, &options).unwrap_err(),
+            Error::TooLarge
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn size_mismatch_error_test() {
         assert_eq!(
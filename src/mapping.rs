@@ -0,0 +1,291 @@
+use crate::{
+    check_scale, normalize_rotation, rotate::scaled_rotation_matrix, Affine, Downsampler, Error,
+    RotationResult, RotspriteOptions, Upscale,
+};
+
+/// Map points between a source sprite and its rotated version.
+///
+/// Useful for attachment points defined on the source sprite, such as hands, muzzle flashes or hitbox corners.
+/// Positions are in pixels where `(0.0, 0.0)` is the upper left corner of the upper left pixel, so the center of a pixel is at `(x + 0.5, y + 0.5)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointMapping {
+    /// Source to output.
+    matrix: Affine,
+    /// Output to source.
+    inverse: Affine,
+    /// Position of the pivot in the output.
+    pivot: (f64, f64),
+}
+
+impl PointMapping {
+    /// Calculate the mapping [`crate::rotsprite_with_options`] uses for a sprite with the size `(width, height)`, without rotating it.
+    ///
+    /// When [`RotspriteOptions::offset_search`] is used the mapping can be off by a fraction of a pixel, use [`RotationResult::mapping`] for an exact mapping in that case.
+    /// [`RotspriteOptions::trim`] depends on the pixels and is ignored, use [`RotationResult::mapping`] as well.
+    /// Only the built-in [`crate::Upscale`] levels are supported, for other upscalers use [`RotationResult::mapping`].
+    /// When rotating by angles that aren't multiples of 90 degrees, points land in the output pixel showing them:
+    /// downsamplers that aren't [`Downsampler::is_nearest`] are assumed to show the center of each block.
+    ///
+    /// The pixel type `P` selects the [`Downsampler`] implementation, for example `PointMapping::new::<u32, _, _, _>(..)`.
+    /// The rotation and [`RotspriteOptions::scale`] are validated the same way as when rotating.
+    pub fn new<P, S, D, C>(
+        width: usize,
+        height: usize,
+        rotation: f64,
+        options: &RotspriteOptions<S, Upscale, D, C>,
+    ) -> Result<Self, Error>
+    where
+        D: Downsampler<P>,
+    {
+        let rotation = normalize_rotation(rotation)?;
+        check_scale(options.scale, options.upscale.factor())?;
+
+        let nearest = options.downsample.is_nearest();
+
+        let matrix = options.flip.matrix(width, height).then(source_matrix(
+            width,
+            height,
+            rotation,
+            options.scale,
+            options.upscale.factor(),
+            nearest,
+        ));
+        let pivot = options.pivot_or_center(width, height);
+        let rotated_pivot = matrix.apply(pivot);
+
+        // Move to the canvas the same way the rotated sprite is moved
+        let mapping = match options
+            .output_size
+            .canvas((width, height), pivot, rotated_pivot)
+        {
+            Some((_, (x, y))) => {
+                let (x, y) = (x as f64, y as f64);
+
+                Self::from_matrix(
+                    matrix.then(Affine::translation(-x, -y)),
+                    (rotated_pivot.0 - x, rotated_pivot.1 - y),
+                )
+            }
+            None => Self::from_matrix(matrix, rotated_pivot),
+        };
+
+        Ok(mapping)
+    }
+
+    /// Construct from a source to output matrix.
+    pub(crate) fn from_matrix(matrix: Affine, pivot: (f64, f64)) -> Self {
+        let inverse = matrix
+            .inverse()
+            .expect("matrices of validated rotations and scales can always be inverted");
+
+        Self {
+            matrix,
+            inverse,
+            pivot,
+        }
+    }
+
+    /// Map a position `(x, y)` in the source to the position in the output.
+    #[must_use]
+    pub fn map(&self, point: (f64, f64)) -> (f64, f64) {
+        self.matrix.apply(point)
+    }
+
+    /// Map a position `(x, y)` in the output back to the position in the source.
+    #[must_use]
+    pub fn unmap(&self, point: (f64, f64)) -> (f64, f64) {
+        self.inverse.apply(point)
+    }
+
+    /// Map positions in the source to positions in the output.
+    #[must_use]
+    pub fn map_points(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        points.iter().map(|point| self.map(*point)).collect()
+    }
+
+    /// Map positions in the output back to positions in the source.
+    #[must_use]
+    pub fn unmap_points(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        points.iter().map(|point| self.unmap(*point)).collect()
+    }
+
+    /// Position `(x, y)` of the pivot in the output.
+    #[must_use]
+    pub fn pivot(&self) -> (f64, f64) {
+        self.pivot
+    }
+
+    /// Matrix mapping positions in the source to positions in the output.
+    #[must_use]
+    pub fn matrix(&self) -> Affine {
+        self.matrix
+    }
+}
+
+impl<P> RotationResult<P> {
+    /// Exact mapping between the source and this output.
+    #[must_use]
+    pub fn mapping(&self) -> PointMapping {
+        PointMapping::from_matrix(self.matrix(), self.pivot())
+    }
+}
+
+/// Matrix mapping the source to the output of the rotsprite algorithm without an offset search.
+fn source_matrix(
+    width: usize,
+    height: usize,
+    rotation: f64,
+    scale: f64,
+    factor: usize,
+    nearest: bool,
+) -> Affine {
+    // Nothing gets rotated
+    if rotation == 0.0 && scale == 1.0 {
+        return Affine::IDENTITY;
+    }
+
+    let fscale = factor as f64;
+
+    Affine::scale(fscale, fscale).then(scaled_rotation_matrix(
+        width * factor,
+        height * factor,
+        rotation,
+        scale,
+        factor,
+        (0.0, 0.0),
+        nearest,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn mapping_matches_rotation() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12].to_vec();
+        for output_size in [
+            OutputSize::Expand,
+            OutputSize::KeepSource,
+            OutputSize::Custom(7, 2),
+        ] {
+            for (scale, flip) in [(1.0, Flip::None), (1.5, Flip::None), (1.0, Flip::Both)] {
+                let options = RotspriteOptions::new()
+                    .with_pivot((1.0, 2.0))
+                    .with_output_size(output_size)
+                    .with_scale(scale)
+                    .with_flip(flip);
+
+                for rotation in [0.0, 30.0, 90.0, 137.0, 270.0, -45.0] {
+                    let rotated = rotsprite_with_options(&buf, &0, 4, rotation, &options)?;
+                    let mapping = PointMapping::new::<i32, _, _, _>(4, 3, rotation, &options)?;
+                    assert_eq!(mapping, rotated.mapping());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn mapping_with_any_options() -> Result<(), Error> {
+        // Downsamplers other than nearest-neighbour map the same way as the rotation
+        let buf = [1_u32, 2, 3, 4, 5, 6];
+        let options = RotspriteOptions::new()
+            .with_anti_aliasing()
+            .with_palette(SourcePalette(EuclideanDistance));
+        let rotated = rotsprite_with_options(&buf, &0, 3, 30.0, &options)?;
+        assert_eq!(
+            PointMapping::new::<u32, _, _, _>(3, 2, 30.0, &options)?,
+            rotated.mapping()
+        );
+
+        // Custom nearest-neighbour downsamplers map like the built-in one
+        struct BlockNearest;
+        impl Downsampler<i32> for BlockNearest {
+            fn downsample(&self, block: &Block<'_, i32>) -> i32 {
+                *block.nearest()
+            }
+
+            fn is_nearest(&self) -> bool {
+                true
+            }
+        }
+        let options = RotspriteOptions::new().with_downsample(BlockNearest);
+        let rotated = rotsprite_with_options(
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+            &0,
+            4,
+            30.0,
+            &options,
+        )?;
+        assert_eq!(PointMapping::new(4, 3, 30.0, &options)?, rotated.mapping());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mapping_lands_on_pixels() -> Result<(), Error> {
+        // The centers of source pixels land on output pixels with the same color
+        let buf = (1..=48).collect::<Vec<_>>();
+        for rotation in [10.0, 30.0, 45.0, 137.0, 200.0] {
+            let rotated = rotsprite(&buf, &0, 8, rotation)?;
+            let (width, height) = rotated.size();
+            let mapping = rotated.mapping();
+
+            let landed = (0..48)
+                .filter(|index| {
+                    let center = ((index % 8) as f64 + 0.5, (index / 8) as f64 + 0.5);
+                    let (x, y) = mapping.map(center);
+
+                    x >= 0.0
+                        && y >= 0.0
+                        && (x as usize) < width
+                        && (y as usize) < height
+                        && rotated.pixels()[y as usize * width + x as usize] == buf[*index]
+                })
+                .count();
+            assert!(landed >= 40, "{rotation}: {landed}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_mapping() {
+        assert_eq!(
+            PointMapping::new::<i32, _, _, _>(4, 2, f64::NAN, &RotspriteOptions::new())
+                .unwrap_err(),
+            Error::InvalidAngle
+        );
+        for scale in [0.0, 1e-200, f64::INFINITY] {
+            assert_eq!(
+                PointMapping::new::<i32, _, _, _>(
+                    4,
+                    2,
+                    30.0,
+                    &RotspriteOptions::new().with_scale(scale)
+                )
+                .unwrap_err(),
+                Error::InvalidScale
+            );
+        }
+    }
+
+    #[test]
+    fn map_and_unmap() -> Result<(), Error> {
+        let mapping = PointMapping::new::<i32, _, _, _>(4, 2, 90.0, &RotspriteOptions::new())?;
+        let points = [(0.0, 0.0), (4.0, 0.0), (0.5, 1.5)];
+
+        let mapped = mapping.map_points(&points);
+        for ((x1, y1), (x2, y2)) in mapped.iter().zip([(2.0, 0.0), (2.0, 4.0), (0.5, 0.5)]) {
+            assert!((x1 - x2).abs() < 1e-9 && (y1 - y2).abs() < 1e-9);
+        }
+
+        for ((x1, y1), (x2, y2)) in mapping.unmap_points(&mapped).iter().zip(points) {
+            assert!((x1 - x2).abs() < 1e-9 && (y1 - y2).abs() < 1e-9);
+        }
+
+        Ok(())
+    }
+}
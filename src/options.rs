@@ -0,0 +1,589 @@
+use crate::{
+    color::Exact,
+    rotate::{flip_horizontal, flip_vertical, rotate180},
+    Affine, AlphaWeighted, AnyColor, ImageView,
+};
+
+/// How much the sprite is upscaled before it's rotated.
+///
+/// Higher levels give smoother results but the intermediate buffer grows quadratically, at [`Upscale::X8`] it's 64 times the size of the source.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Upscale {
+    /// Don't upscale, rotate the pixels directly.
+    ///
+    /// This is the fastest but produces a naive rotation.
+    X1,
+    /// Apply the scale2x algorithm once.
+    X2,
+    /// Apply the scale3x algorithm once.
+    X3,
+    /// Apply the scale2x algorithm twice.
+    X4,
+    /// Apply the scale2x algorithm three times.
+    ///
+    /// This is the level used by the original RotSprite algorithm.
+    #[default]
+    X8,
+    /// Apply the scale3x algorithm twice.
+    ///
+    /// Some sprites with diagonal lines at odd angles look better at this level than at [`Upscale::X8`].
+    X9,
+    /// Apply the scale2x algorithm four times.
+    X16,
+}
+
+impl Upscale {
+    /// Size multiplier of the intermediate buffer in each dimension.
+    pub fn factor(self) -> usize {
+        let (scale2x_passes, scale3x_passes) = self.passes();
+
+        2usize.pow(scale2x_passes as u32) * 3usize.pow(scale3x_passes as u32)
+    }
+
+    /// How many times the scale2x and the scale3x algorithms must be applied.
+    pub(crate) fn passes(self) -> (usize, usize) {
+        match self {
+            Upscale::X1 => (0, 0),
+            Upscale::X2 => (1, 0),
+            Upscale::X3 => (0, 1),
+            Upscale::X4 => (2, 0),
+            Upscale::X8 => (3, 0),
+            Upscale::X9 => (0, 2),
+            Upscale::X16 => (4, 0),
+        }
+    }
+}
+
+/// How neighbours outside of the sprite are treated while upscaling.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Border {
+    /// Use the closest pixel inside the sprite.
+    ///
+    /// This is the behaviour of the original scale2x algorithm.
+    #[default]
+    Clamp,
+    /// Use the pixel on the opposite side of the sprite, for textures that tile.
+    Wrap,
+    /// Use the empty color, so silhouettes touching the edges of the sprite get smoothed.
+    Empty,
+}
+
+impl Border {
+    /// Get the pixel at `(x, y)` of a sprite, positions outside of the sprite are resolved with the border mode.
+    ///
+    /// Useful for implementing a [`crate::Upscaler`].
+    /// Sprites without pixels only consist of the empty color.
+    #[inline(always)]
+    pub fn pixel<'a, P, I>(self, image: &'a I, (x, y): (isize, isize), empty_color: &'a P) -> &'a P
+    where
+        I: ImageView<P>,
+    {
+        let (width, height) = image.size();
+        let (iwidth, iheight) = (width as isize, height as isize);
+        if x >= 0 && y >= 0 && x < iwidth && y < iheight {
+            return image.pixel(x as usize, y as usize);
+        }
+
+        if width == 0 || height == 0 {
+            return empty_color;
+        }
+
+        match self {
+            Border::Clamp => image.pixel(
+                x.clamp(0, iwidth - 1) as usize,
+                y.clamp(0, iheight - 1) as usize,
+            ),
+            Border::Wrap => image.pixel(
+                x.rem_euclid(iwidth) as usize,
+                y.rem_euclid(iheight) as usize,
+            ),
+            Border::Empty => empty_color,
+        }
+    }
+}
+
+/// How the upscaled rotated image is reduced back to the output size.
+///
+/// Every output pixel covers a block of `factor * factor` samples of the upscaled image.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Downsample {
+    /// Keep a single sample of each block.
+    ///
+    /// This is the fastest but can drop thin details depending on the angle.
+    #[default]
+    Nearest,
+    /// Keep the sample closest to the center of each block.
+    ///
+    /// Samples outside of the rotated sprite count as the empty color.
+    Center,
+    /// Keep the most frequent color in each block.
+    ///
+    /// Samples outside of the rotated sprite count as the empty color.
+    Majority(TieBreak),
+}
+
+/// Which color wins when multiple colors are the most frequent in a block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TieBreak {
+    /// Prefer the color with a sample closest to the center of the block.
+    #[default]
+    Center,
+    /// Prefer any color that's not the empty color, falling back to the one closest to the center.
+    ///
+    /// This keeps outlines from being eaten away at the edges of the sprite.
+    NonEmpty,
+}
+
+/// Size of the canvas the rotated sprite is drawn on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputSize {
+    /// Grow the canvas so the whole rotated sprite fits.
+    ///
+    /// The width and the height will be swapped at angles of 90.0 and 270.0.
+    #[default]
+    Expand,
+    /// Keep the size of the source sprite, cropping everything that doesn't fit.
+    ///
+    /// The pivot stays at the same position as in the source, rounded to whole pixels.
+    KeepSource,
+    /// Use a canvas with the size `(width, height)`, cropping everything that doesn't fit.
+    ///
+    /// The pivot is placed in the center of the canvas, rounded to whole pixels.
+    Custom(usize, usize),
+}
+
+impl OutputSize {
+    /// Size of the canvas and the whole pixel position of the canvas on the expanded rotated sprite.
+    ///
+    /// Returns `None` when the canvas should fit the whole rotated sprite.
+    /// The size is of the source sprite, the pivot is in the source and the rotated pivot in the expanded rotated sprite.
+    pub(crate) fn canvas(
+        self,
+        (width, height): (usize, usize),
+        pivot: (f64, f64),
+        (rotated_pivot_x, rotated_pivot_y): (f64, f64),
+    ) -> Option<((usize, usize), (isize, isize))> {
+        let ((canvas_width, canvas_height), (target_x, target_y)) = match self {
+            OutputSize::Expand => return None,
+            OutputSize::KeepSource => ((width, height), pivot),
+            OutputSize::Custom(width, height) => {
+                ((width, height), (width as f64 / 2.0, height as f64 / 2.0))
+            }
+        };
+
+        // Move the pivot to the target position in whole pixels
+        Some((
+            (canvas_width, canvas_height),
+            (
+                (rotated_pivot_x - target_x).round() as isize,
+                (rotated_pivot_y - target_y).round() as isize,
+            ),
+        ))
+    }
+}
+
+/// Mirroring applied to the sprite before it's rotated.
+///
+/// Useful for changing the facing direction of a character in the same call as rotating it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flip {
+    /// Don't mirror the sprite.
+    #[default]
+    None,
+    /// Mirror left and right.
+    Horizontal,
+    /// Mirror top and bottom.
+    Vertical,
+    /// Mirror both left and right and top and bottom.
+    Both,
+}
+
+impl Flip {
+    /// Matrix mapping positions in a sprite with the size `(width, height)` to positions in the mirrored sprite.
+    pub(crate) fn matrix(self, width: usize, height: usize) -> Affine {
+        let (width, height) = (width as f64, height as f64);
+
+        match self {
+            Flip::None => Affine::IDENTITY,
+            Flip::Horizontal => Affine([[-1.0, 0.0, width], [0.0, 1.0, 0.0]]),
+            Flip::Vertical => Affine([[1.0, 0.0, 0.0], [0.0, -1.0, height]]),
+            Flip::Both => Affine([[-1.0, 0.0, width], [0.0, -1.0, height]]),
+        }
+    }
+
+    /// Split into reversing the order of the rows and a half turn, returned as `(reversed_rows, half_turn)`.
+    ///
+    /// Mirroring left and right is mirroring top and bottom followed by a half turn.
+    pub(crate) fn split(self) -> (bool, bool) {
+        match self {
+            Flip::None => (false, false),
+            Flip::Horizontal => (true, true),
+            Flip::Vertical => (true, false),
+            Flip::Both => (false, true),
+        }
+    }
+
+    /// Mirror a sprite, returns `None` when nothing changes so the sprite doesn't have to be copied.
+    pub(crate) fn apply<P, I>(self, image: &I) -> Option<Vec<P>>
+    where
+        P: Clone,
+        I: ImageView<P>,
+    {
+        let (_, _, flipped) = match self {
+            Flip::None => return None,
+            Flip::Horizontal => flip_horizontal(image),
+            Flip::Vertical => flip_vertical(image),
+            Flip::Both => rotate180(image),
+        };
+
+        Some(flipped)
+    }
+}
+
+/// Options for tweaking how a sprite is rotated.
+///
+/// The default options produce the same result as [`crate::rotsprite`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotspriteOptions<S = Exact, U = Upscale, D = Downsample, C = AnyColor> {
+    /// Upscale level applied before rotating.
+    ///
+    /// Lower levels trade quality for speed, which can be useful for small or short-lived sprites like particles.
+    /// Can be any [`crate::Upscaler`], such as [`crate::Eagle`] or [`crate::Epx`] for a different look.
+    pub upscale: U,
+
+    /// How neighbours outside of the sprite are treated while upscaling.
+    pub border: Border,
+
+    /// Predicate deciding which neighbouring colors are treated as equal while upscaling.
+    ///
+    /// The original RotSprite algorithm treats similar colors as equal so subtle shading still gets smoothed edges.
+    /// By default only exactly equal colors are treated as equal, see [`crate::RgbaDistance`] for a color distance based alternative.
+    pub similarity: S,
+
+    /// Amount of sub-pixel offsets of the rotation origin tried along each axis.
+    ///
+    /// Like the original RotSprite algorithm, a grid of `offset_search * offset_search` offsets within a single source pixel is rotated at the upscaled level.
+    /// The result with the fewest pixels differing from a nearest-neighbour rotation of the source is kept, which reduces artifacts at the cost of rotating multiple times.
+    ///
+    /// Values of `0` and `1` disable the search and only rotate around the origin.
+    pub offset_search: usize,
+
+    /// How blocks of the upscaled rotated image are reduced to single output pixels.
+    ///
+    /// Can be any [`crate::Downsampler`], such as [`crate::AlphaWeighted`] for smooth edges.
+    pub downsample: D,
+
+    /// Whether single pixel details that vanished while rotating are drawn back.
+    ///
+    /// Every source pixel is mapped to its position in the output, when its color can't be found in the surrounding pixels it's re-inserted.
+    /// This protects small features like eyes, highlights and thin outlines.
+    pub restore_details: bool,
+
+    /// Point in the source sprite `(x, y)` in pixels the sprite rotates around.
+    ///
+    /// - When `None` is used, the center of the sprite is used as the pivot.
+    /// - The position of the pivot in the rotated sprite is returned by [`crate::RotationResult::pivot`], which can be used to place the sprite so it doesn't wobble.
+    pub pivot: Option<(f64, f64)>,
+
+    /// Size of the canvas the rotated sprite is drawn on.
+    ///
+    /// By default the canvas grows to fit the whole rotated sprite, for tiles and icons it can be useful to keep the size of the source instead.
+    pub output_size: OutputSize,
+
+    /// Whether fully empty rows and columns at the borders of the output are removed.
+    ///
+    /// Applied after the sprite is drawn on the canvas, so it also trims the canvas of [`RotspriteOptions::output_size`].
+    /// The position of the removed area is reflected in [`crate::RotationResult::offset`] and [`crate::RotationResult::pivot`].
+    pub trim: bool,
+
+    /// Uniform scale applied together with the rotation.
+    ///
+    /// The upscaled image is sampled at a different rate instead of resizing the rotated output afterwards, so the pixel-art look is kept at non-integer scales like `1.5`.
+    /// Must be positive and not vanishingly small, the default of `1.0` keeps the size.
+    pub scale: f64,
+
+    /// Mirroring applied to the sprite before it's rotated.
+    ///
+    /// The pivot and all mappings stay in the coordinates of the source before mirroring.
+    /// To mirror left and right the sprite is upscaled mirrored top and bottom and the upscaled result is turned by half, so custom upscalers should treat all directions the same.
+    pub flip: Flip,
+
+    /// Maximum amount of pixels of any buffer allocated while rotating, including the upscaled intermediate image.
+    ///
+    /// The upscaled image is `factor * factor` times the size of the source, 64 times for the default [`Upscale::X8`].
+    /// When a buffer would be larger [`crate::Error::TooLarge`] is returned instead of allocating it, which protects against corrupted or malicious sprites.
+    /// When `None` is used only buffers that can't be addressed in memory are rejected.
+    pub max_pixels: Option<usize>,
+
+    /// Restriction on which colors can end up in the rotated sprite.
+    ///
+    /// By default any color is allowed, use [`crate::SourcePalette`] to snap blended colors back to the palette of the source.
+    pub palette: C,
+}
+
+impl<S, U, D, C> Default for RotspriteOptions<S, U, D, C>
+where
+    S: Default,
+    U: Default,
+    D: Default,
+    C: Default,
+{
+    fn default() -> Self {
+        Self {
+            upscale: U::default(),
+            border: Border::default(),
+            similarity: S::default(),
+            offset_search: 0,
+            downsample: D::default(),
+            restore_details: false,
+            pivot: None,
+            output_size: OutputSize::default(),
+            trim: false,
+            scale: 1.0,
+            flip: Flip::default(),
+            max_pixels: None,
+            palette: C::default(),
+        }
+    }
+}
+
+impl RotspriteOptions {
+    /// Setup the options with the same behaviour as the original RotSprite algorithm.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S, U, D, C> RotspriteOptions<S, U, D, C> {
+    /// Set the upscale level or algorithm applied before rotating.
+    ///
+    /// Accepts any [`crate::Upscaler`] implementation.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::upscale`]
+    #[must_use]
+    pub fn with_upscale<U2>(self, upscale: U2) -> RotspriteOptions<S, U2, D, C> {
+        RotspriteOptions {
+            upscale,
+            border: self.border,
+            similarity: self.similarity,
+            offset_search: self.offset_search,
+            downsample: self.downsample,
+            restore_details: self.restore_details,
+            pivot: self.pivot,
+            output_size: self.output_size,
+            trim: self.trim,
+            scale: self.scale,
+            flip: self.flip,
+            max_pixels: self.max_pixels,
+            palette: self.palette,
+        }
+    }
+
+    /// Set how neighbours outside of the sprite are treated while upscaling.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::border`]
+    #[must_use]
+    pub fn with_border(mut self, border: Border) -> Self {
+        self.border = border;
+
+        self
+    }
+
+    /// Set the predicate deciding which colors are treated as equal while upscaling.
+    ///
+    /// Accepts any [`crate::Similarity`] implementation, including closures with the signature `Fn(&P, &P) -> bool`.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::similarity`]
+    #[must_use]
+    pub fn with_similarity<S2>(self, similarity: S2) -> RotspriteOptions<S2, U, D, C> {
+        RotspriteOptions {
+            upscale: self.upscale,
+            border: self.border,
+            similarity,
+            offset_search: self.offset_search,
+            downsample: self.downsample,
+            restore_details: self.restore_details,
+            pivot: self.pivot,
+            output_size: self.output_size,
+            trim: self.trim,
+            scale: self.scale,
+            flip: self.flip,
+            max_pixels: self.max_pixels,
+            palette: self.palette,
+        }
+    }
+
+    /// Set the amount of sub-pixel offsets tried along each axis to find the rotation with the fewest artifacts.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::offset_search`]
+    #[must_use]
+    pub fn with_offset_search(mut self, offset_search: usize) -> Self {
+        self.offset_search = offset_search;
+
+        self
+    }
+
+    /// Set how blocks of the upscaled rotated image are reduced to single output pixels.
+    ///
+    /// Accepts any [`crate::Downsampler`] implementation.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::downsample`]
+    #[must_use]
+    pub fn with_downsample<D2>(self, downsample: D2) -> RotspriteOptions<S, U, D2, C> {
+        RotspriteOptions {
+            upscale: self.upscale,
+            border: self.border,
+            similarity: self.similarity,
+            offset_search: self.offset_search,
+            downsample,
+            restore_details: self.restore_details,
+            pivot: self.pivot,
+            output_size: self.output_size,
+            trim: self.trim,
+            scale: self.scale,
+            flip: self.flip,
+            max_pixels: self.max_pixels,
+            palette: self.palette,
+        }
+    }
+
+    /// Smooth the edges of the rotated sprite by averaging all upscaled samples covering an output pixel.
+    ///
+    /// Colors are weighted by their alpha, so this requires pixels implementing [`crate::RgbaPixel`].
+    /// New colors are introduced, without this the output only contains colors from the source.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::downsample`]
+    #[must_use]
+    pub fn with_anti_aliasing(self) -> RotspriteOptions<S, U, AlphaWeighted, C> {
+        self.with_downsample(AlphaWeighted)
+    }
+
+    /// Set which colors can end up in the rotated sprite.
+    ///
+    /// Accepts any [`crate::ColorConstraint`] implementation, such as [`crate::SourcePalette`].
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::palette`]
+    #[must_use]
+    pub fn with_palette<C2>(self, palette: C2) -> RotspriteOptions<S, U, D, C2> {
+        RotspriteOptions {
+            upscale: self.upscale,
+            border: self.border,
+            similarity: self.similarity,
+            offset_search: self.offset_search,
+            downsample: self.downsample,
+            restore_details: self.restore_details,
+            pivot: self.pivot,
+            output_size: self.output_size,
+            trim: self.trim,
+            scale: self.scale,
+            flip: self.flip,
+            max_pixels: self.max_pixels,
+            palette,
+        }
+    }
+
+    /// Set whether single pixel details that vanished while rotating are drawn back.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::restore_details`]
+    #[must_use]
+    pub fn with_restore_details(mut self, restore_details: bool) -> Self {
+        self.restore_details = restore_details;
+
+        self
+    }
+
+    /// Set the point in the source sprite `(x, y)` in pixels the sprite rotates around.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::pivot`]
+    #[must_use]
+    pub fn with_pivot(mut self, pivot: (f64, f64)) -> Self {
+        self.pivot = Some(pivot);
+
+        self
+    }
+
+    /// Set the maximum amount of pixels of any buffer allocated while rotating.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::max_pixels`]
+    #[must_use]
+    pub fn with_max_pixels(mut self, max_pixels: usize) -> Self {
+        self.max_pixels = Some(max_pixels);
+
+        self
+    }
+
+    /// Set the size of the canvas the rotated sprite is drawn on.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::output_size`]
+    #[must_use]
+    pub fn with_output_size(mut self, output_size: OutputSize) -> Self {
+        self.output_size = output_size;
+
+        self
+    }
+
+    /// Set whether fully empty rows and columns at the borders of the output are removed.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::trim`]
+    #[must_use]
+    pub fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+
+        self
+    }
+
+    /// Set the uniform scale applied together with the rotation.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::scale`]
+    #[must_use]
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+
+        self
+    }
+
+    /// Set the mirroring applied to the sprite before it's rotated.
+    ///
+    /// # Sets field(s)
+    ///
+    /// - [`RotspriteOptions::flip`]
+    #[must_use]
+    pub fn with_flip(mut self, flip: Flip) -> Self {
+        self.flip = flip;
+
+        self
+    }
+
+    /// Get the pivot, falling back to the center of a sprite with the size `(width, height)`.
+    pub(crate) fn pivot_or_center(&self, width: usize, height: usize) -> (f64, f64) {
+        self.pivot
+            .unwrap_or((width as f64 / 2.0, height as f64 / 2.0))
+    }
+}
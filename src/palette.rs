@@ -0,0 +1,175 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use crate::{ColorDistance, EuclideanDistance, ImageView};
+
+/// Restriction on which colors can end up in the rotated sprite.
+///
+/// Implement this to try other strategies than the ones provided, see [`crate::RotspriteOptions::with_palette`] for using it.
+pub trait ColorConstraint<P> {
+    /// Replace the colors of the rotated pixels that aren't allowed.
+    ///
+    /// `source` is the sprite before it was rotated.
+    fn constrain<I>(&self, source: &I, empty_color: &P, pixels: &mut [P])
+    where
+        I: ImageView<P>;
+}
+
+/// Allow any color in the rotated sprite.
+///
+/// Without blending downsamplers like [`crate::AlphaWeighted`] only colors of the source end up in the rotated sprite anyway.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnyColor;
+
+impl<P> ColorConstraint<P> for AnyColor {
+    fn constrain<I>(&self, _source: &I, _empty_color: &P, _pixels: &mut [P])
+    where
+        I: ImageView<P>,
+    {
+    }
+}
+
+/// Snap every pixel of the rotated sprite to the closest color of the source sprite.
+///
+/// The palette consists of all colors of the source and the empty color, the distance between colors is measured with the [`ColorDistance`].
+/// Useful to keep the palette of the sprite intact when blending with [`crate::AlphaWeighted`] or similar.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourcePalette<C = EuclideanDistance>(pub C);
+
+impl<P, C> ColorConstraint<P> for SourcePalette<C>
+where
+    P: Eq + Hash + Clone,
+    C: ColorDistance<P>,
+{
+    fn constrain<I>(&self, source: &I, empty_color: &P, pixels: &mut [P])
+    where
+        I: ImageView<P>,
+    {
+        // Collect all unique colors, in the order they appear so ties are resolved the same way every time
+        let mut unique = HashSet::new();
+        let mut palette = std::iter::once(empty_color)
+            .chain((0..source.height()).flat_map(|y| source.row(y)))
+            .filter(|pixel| unique.insert(*pixel))
+            .enumerate()
+            .map(|(index, color)| (self.0.key(color), index, color))
+            .collect::<Vec<_>>();
+        palette.sort_by(|(a, _, _), (b, _, _)| a.total_cmp(b));
+
+        // Rotated sprites only have a few distinct colors, so each is only snapped once
+        let mut snapped = HashMap::new();
+        for pixel in pixels.iter_mut() {
+            if unique.contains(&*pixel) {
+                continue;
+            }
+
+            let closest = snapped
+                .entry(pixel.clone())
+                .or_insert_with(|| closest(&self.0, &palette, pixel).clone());
+            *pixel = closest.clone();
+        }
+    }
+}
+
+/// Find the closest color in a palette sorted by key, the first color of the source wins ties.
+///
+/// Starting at the key of the pixel, colors are only compared while their keys are close enough to be closer than the best one so far.
+fn closest<'a, P, C>(distance: &C, palette: &[(f64, usize, &'a P)], pixel: &P) -> &'a P
+where
+    C: ColorDistance<P>,
+{
+    let key = distance.key(pixel);
+    let start = palette.partition_point(|(color_key, _, _)| *color_key < key);
+
+    let mut best: Option<(f64, usize, &P)> = None;
+    let mut compare = |(color_key, index, color): &(f64, usize, &'a P)| {
+        if best.is_some_and(|(best_distance, _, _)| (color_key - key).abs() > best_distance) {
+            return false;
+        }
+
+        let color_distance = distance.distance(pixel, color);
+        if best.is_none_or(|(best_distance, best_index, _)| {
+            color_distance
+                .total_cmp(&best_distance)
+                .then(index.cmp(&best_index))
+                .is_lt()
+        }) {
+            best = Some((color_distance, *index, color));
+        }
+
+        true
+    };
+
+    // Keys only get further away in both directions
+    for color in &palette[start..] {
+        if !compare(color) {
+            break;
+        }
+    }
+    for color in palette[..start].iter().rev() {
+        if !compare(color) {
+            break;
+        }
+    }
+
+    best.map_or(palette[0].2, |(_, _, color)| color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubImage;
+
+    #[test]
+    fn source_palette() {
+        let source = [0xFF_FF_00_00_u32, 0xFF_00_00_FF];
+        let mut pixels = [0xFF_FF_00_00, 0xFF_E0_00_10, 0x80_00_00_F0, 0x10_00_00_00];
+        SourcePalette(EuclideanDistance).constrain(
+            &SubImage::packed(&source, 2, 1),
+            &0,
+            &mut pixels,
+        );
+        assert_eq!(pixels, [0xFF_FF_00_00, 0xFF_FF_00_00, 0xFF_00_00_FF, 0]);
+
+        // Custom distances only looking at a single channel
+        let mut pixels = [5, 9];
+        let distance = |a: &i32, b: &i32| f64::from((a - b).abs());
+        SourcePalette(distance).constrain(&SubImage::packed(&[4, 10], 2, 1), &0, &mut pixels);
+        assert_eq!(pixels, [4, 10]);
+
+        // Ties go to the color that comes first in the source
+        let mut pixels = [7];
+        SourcePalette(distance).constrain(&SubImage::packed(&[9, 5], 2, 1), &0, &mut pixels);
+        assert_eq!(pixels, [9]);
+
+        let mut pixels = [5];
+        AnyColor.constrain(&SubImage::packed(&[4], 1, 1), &0, &mut pixels);
+        assert_eq!(pixels, [5]);
+    }
+
+    #[test]
+    fn closest_matches_linear_search() {
+        let colors = (0..200_u32)
+            .map(|i| i.wrapping_mul(2_654_435_761))
+            .collect::<Vec<_>>();
+        let mut palette = colors
+            .iter()
+            .enumerate()
+            .map(|(index, color)| (EuclideanDistance.key(color), index, color))
+            .collect::<Vec<_>>();
+        palette.sort_by(|(a, _, _), (b, _, _)| a.total_cmp(b));
+
+        for pixel in (0..100_u32).map(|i| i.wrapping_mul(40_503).rotate_left(i)) {
+            let expected = colors
+                .iter()
+                .min_by(|a, b| {
+                    EuclideanDistance
+                        .distance(&pixel, a)
+                        .total_cmp(&EuclideanDistance.distance(&pixel, b))
+                })
+                .unwrap();
+            assert_eq!(closest(&EuclideanDistance, &palette, &pixel), expected);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{ImageView, ImageViewMut};
+
+// Re-insert single pixel details of the source that got lost while rotating
+//
+// Every pixel in the source is mapped to its position in the rotated image with `map`,
+// when its color can't be found in the surrounding 3x3 pixels it's drawn back at that position
+pub fn restore_details<P, M, I, R>(image: &I, empty_color: &P, rotated: &mut R, map: M)
+where
+    P: Eq + Clone,
+    M: Fn(f64, f64) -> (f64, f64),
+    I: ImageView<P>,
+    R: ImageViewMut<P>,
+{
+    let (width, height) = image.size();
+    let (rotated_width, rotated_height) = rotated.size();
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = image.pixel(x, y);
+            if pixel == empty_color {
+                continue;
+            }
+
+            // Map the center of the source pixel
+            let (dst_x, dst_y) = map(x as f64 + 0.5, y as f64 + 0.5);
+            if dst_x < 0.0 || dst_y < 0.0 {
+                continue;
+            }
+            let (dst_x, dst_y) = (dst_x as usize, dst_y as usize);
+            if dst_x >= rotated_width || dst_y >= rotated_height {
+                continue;
+            }
+
+            // Check if the color still exists in the neighbourhood
+            let found = (dst_y.saturating_sub(1)..(dst_y + 2).min(rotated_height)).any(|y| {
+                (dst_x.saturating_sub(1)..(dst_x + 2).min(rotated_width))
+                    .any(|x| rotated.pixel(x, y) == pixel)
+            });
+
+            if !found {
+                *rotated.pixel_mut(dst_x, dst_y) = pixel.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SubImage, SubImageMut};
+
+    #[test]
+    fn restore_missing_pixel() {
+        #[rustfmt::skip]
+        let buf = [
+            0, 0, 0, 0,
+            0, 1, 1, 0,
+            0, 1, 2, 0,
+            0, 0, 0, 0,
+        ];
+
+        // The `2` got lost
+        let mut rotated = [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0];
+        restore_details(
+            &SubImage::packed(&buf, 4, 4),
+            &0,
+            &mut SubImageMut::new(&mut rotated, 4).unwrap(),
+            |x, y| (x, y),
+        );
+        assert_eq!(rotated, buf);
+
+        // Colors that still exist nearby are not restored
+        let mut rotated = [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 2, 0, 0, 0, 0];
+        restore_details(
+            &SubImage::packed(&buf, 4, 4),
+            &0,
+            &mut SubImageMut::new(&mut rotated, 4).unwrap(),
+            |x, y| (x, y),
+        );
+        assert_eq!(rotated, [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 2, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{Affine, ImageView, ImageViewMut};
+
+/// Transformed sprite together with how it relates to the source sprite.
+///
+/// Can be converted into the `(width, height, pixels)` tuple returned by earlier versions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationResult<P> {
+    /// Horizontal size of the output in pixels.
+    width: usize,
+    /// Vertical size of the output in pixels.
+    height: usize,
+    /// Output pixels, row by row.
+    pixels: Vec<P>,
+    /// Applied rotation in degrees, in the 0.0-360.0 range.
+    rotation: f64,
+    /// Maps positions in the source to positions in the output.
+    matrix: Affine,
+    /// Position of the pivot in the output.
+    pivot: (f64, f64),
+}
+
+impl<P> RotationResult<P> {
+    /// Construct from all parts.
+    pub(crate) fn new(
+        (width, height, pixels): (usize, usize, Vec<P>),
+        rotation: f64,
+        matrix: Affine,
+        pivot: (f64, f64),
+    ) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+            rotation,
+            matrix,
+            pivot,
+        }
+    }
+
+    /// Horizontal size of the output in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Vertical size of the output in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Size of the output as `(width, height)` in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Output pixels, row by row.
+    pub fn pixels(&self) -> &[P] {
+        &self.pixels
+    }
+
+    /// Mutable output pixels, row by row.
+    pub(crate) fn pixels_mut(&mut self) -> &mut [P] {
+        &mut self.pixels
+    }
+
+    /// Take the output pixels.
+    pub fn into_pixels(self) -> Vec<P> {
+        self.pixels
+    }
+
+    /// Rotation that got applied in degrees, normalized to the 0.0-360.0 range.
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    /// Offset `(x, y)` of the upper left corner of the output relative to the transformed source origin, in output pixels.
+    ///
+    /// Drawing the output at the position the source would be drawn at plus this offset makes it rotate around the source origin.
+    pub fn offset(&self) -> (f64, f64) {
+        let (x, y) = self.matrix.apply((0.0, 0.0));
+
+        (-x, -y)
+    }
+
+    /// Position `(x, y)` of the pivot in the output, in output pixels.
+    ///
+    /// Drawing the output at the position of the pivot minus this position makes it rotate around the pivot.
+    pub fn pivot(&self) -> (f64, f64) {
+        self.pivot
+    }
+
+    /// Affine matrix mapping positions in the source to positions in the output.
+    ///
+    /// Both are in pixels where `(0.0, 0.0)` is the upper left corner of the upper left pixel.
+    pub fn matrix(&self) -> Affine {
+        self.matrix
+    }
+}
+
+impl<P> From<RotationResult<P>> for (usize, usize, Vec<P>) {
+    fn from(result: RotationResult<P>) -> Self {
+        (result.width, result.height, result.pixels)
+    }
+}
+
+impl<P> ImageView<P> for RotationResult<P> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline(always)]
+    fn row(&self, y: usize) -> &[P] {
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+}
+
+impl<P> ImageViewMut<P> for RotationResult<P> {
+    #[inline(always)]
+    fn row_mut(&mut self, y: usize) -> &mut [P] {
+        &mut self.pixels[y * self.width..(y + 1) * self.width]
+    }
+}
@@ -1,7 +1,12 @@
 use std::f64;
 
+use crate::{
+    downsample::{Block, Downsampler},
+    options::Downsample,
+    Affine, Error, ImageView, RotationResult, SubImage,
+};
+
 // Algorithm for rotating the image
-#[multiversion::multiversion(targets("x86_64+sse3", "x86_64+sse3+avx", "x86_64+sse3+avx2"))]
 pub fn rotate<P>(
     buf: &[P],
     empty_color: &P,
@@ -9,26 +14,109 @@ pub fn rotate<P>(
     height: usize,
     rotation: f64,
     down_scale_factor: usize,
-) -> (usize, usize, Vec<P>)
+) -> Result<RotationResult<P>, Error>
 where
-    P: Clone,
+    P: Eq + Clone,
+{
+    rotate_with_offset(
+        buf,
+        empty_color,
+        width,
+        height,
+        rotation,
+        down_scale_factor,
+        (0.0, 0.0),
+        &Downsample::Nearest,
+    )
+}
+
+// Same as `rotate` but every sample in the source is shifted by a (sub-)pixel offset
+// and each block of samples is reduced to a single pixel with the downsample mode
+//
+// The offset is ignored for the fast path of rotations that are a multiple of 90 degrees
+#[allow(clippy::too_many_arguments)]
+pub fn rotate_with_offset<P, D>(
+    buf: &[P],
+    empty_color: &P,
+    width: usize,
+    height: usize,
+    rotation: f64,
+    down_scale_factor: usize,
+    offset: (f64, f64),
+    downsample: &D,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+    D: Downsampler<P>,
+{
+    let image = SubImage::sized(buf, width, height)?;
+    if down_scale_factor == 0 {
+        return Err(Error::InvalidScale);
+    }
+
+    Ok(rotate_scaled_with_offset(
+        &image,
+        empty_color,
+        rotation,
+        1.0,
+        down_scale_factor,
+        offset,
+        downsample,
+    ))
+}
+
+// Same as `rotate_with_offset` but the rotated image is also scaled by a uniform factor
+//
+// Scaling changes the rate at which the upscaled image is sampled, so a scale of 2.0 takes
+// every sample twice as often and results in an image twice as big
+#[allow(clippy::too_many_arguments)]
+#[multiversion::multiversion(targets("x86_64+sse3", "x86_64+sse3+avx", "x86_64+sse3+avx2"))]
+pub fn rotate_scaled_with_offset<P, D, I>(
+    image: &I,
+    empty_color: &P,
+    rotation: f64,
+    scale: f64,
+    down_scale_factor: usize,
+    (offset_x, offset_y): (f64, f64),
+    downsample: &D,
+) -> RotationResult<P>
+where
+    P: Eq + Clone,
+    D: Downsampler<P>,
+    I: ImageView<P>,
 {
     // Always keep the rotation in the 0.0-360.0 range
     let rotation = rotation.rem_euclid(360.0);
+    let (width, height) = image.size();
+
+    let matrix = scaled_rotation_matrix(
+        width,
+        height,
+        rotation,
+        scale,
+        down_scale_factor,
+        (offset_x, offset_y),
+        downsample.is_nearest(),
+    );
+    let pivot = matrix.apply((width as f64 / 2.0, height as f64 / 2.0));
 
     // If rotation is any of 0.0, 90.0, 180.0 or 270.0 we can do a much faster calculation
-    if rotation % 90.0 == 0.0 {
-        let (width, height, downscaled) = downscale(buf, width, height, down_scale_factor);
+    if is_fast_path(rotation, scale) {
+        let (width, height, downscaled) =
+            downscale_with_downsample(image, empty_color, down_scale_factor, downsample).into();
+        let downscaled_image = SubImage::packed(&downscaled, width, height);
 
-        return if (rotation - 90.0).abs() < f64::EPSILON {
-            rotate90(&downscaled, width, height)
+        let rotated = if (rotation - 90.0).abs() < f64::EPSILON {
+            rotate90(&downscaled_image)
         } else if (rotation - 180.0).abs() < f64::EPSILON {
-            rotate180(&downscaled, width, height)
+            rotate180(&downscaled_image)
         } else if (rotation - 270.0).abs() < f64::EPSILON {
-            rotate270(&downscaled, width, height)
+            rotate270(&downscaled_image)
         } else {
-            (width, height, downscaled.to_vec())
+            (width, height, downscaled)
         };
+
+        return RotationResult::new(rotated, rotation, matrix, pivot);
     }
 
     let fwidth = width as f64;
@@ -38,6 +126,223 @@ where
     let sin = radians.sin();
     let cos = radians.cos();
 
+    // Scaling the rotated image is the same as rotating a scaled image
+    let (min_x, min_y, result_width, result_height) =
+        rotated_bounds(fwidth * scale, fheight * scale, rotation);
+
+    // Find the pixel in the source for a pixel in the upscaled rotated image
+    let sample = |x: usize, y: usize| {
+        let x_with_min = x as f64 + min_x;
+        let y_with_min = y as f64 + min_y;
+
+        let source_x = (x_with_min * cos + y_with_min * sin) / scale + offset_x;
+        let source_y = (y_with_min * cos - x_with_min * sin) / scale + offset_y;
+
+        (source_x >= 0.0 && source_x < fwidth && source_y >= 0.0 && source_y < fheight)
+            .then(|| image.pixel(source_x as usize, source_y as usize))
+    };
+
+    let rotated = resample(
+        sample,
+        empty_color,
+        (result_width as usize, result_height as usize),
+        down_scale_factor,
+        downsample,
+    );
+
+    RotationResult::new(rotated, rotation, matrix, pivot)
+}
+
+// Transform the image with an affine matrix mapping positions in the image to positions in the result
+//
+// Every pixel in the result is sampled `down_scale_factor` times along each axis by mapping it back
+// into the image, the result is moved so it starts at the upper left corner of the bounding box
+//
+// Returns `None` when the matrix can't be inverted
+#[multiversion::multiversion(targets("x86_64+sse3", "x86_64+sse3+avx", "x86_64+sse3+avx2"))]
+pub fn transform<P, D, I>(
+    image: &I,
+    empty_color: &P,
+    matrix: Affine,
+    down_scale_factor: usize,
+    downsample: &D,
+) -> Option<RotationResult<P>>
+where
+    P: Eq + Clone,
+    D: Downsampler<P>,
+    I: ImageView<P>,
+{
+    let inverse = matrix.inverse()?;
+
+    let (width, height) = image.size();
+    let fwidth = width as f64;
+    let fheight = height as f64;
+
+    let (min_x, min_y, result_width, result_height) = transformed_bounds(fwidth, fheight, matrix);
+
+    // Find the pixel in the source for a pixel in the upscaled result
+    let fscale = down_scale_factor as f64;
+    let sample = |x: usize, y: usize| {
+        let (source_x, source_y) =
+            inverse.apply((x as f64 / fscale + min_x, y as f64 / fscale + min_y));
+
+        (source_x >= 0.0 && source_x < fwidth && source_y >= 0.0 && source_y < fheight)
+            .then(|| image.pixel(source_x as usize, source_y as usize))
+    };
+
+    let transformed = resample(
+        sample,
+        empty_color,
+        (
+            result_width * down_scale_factor,
+            result_height * down_scale_factor,
+        ),
+        down_scale_factor,
+        downsample,
+    );
+
+    // Only transformations that aren't axis aligned sample in between the pixels of the source
+    let [[a, b, _], [d, e, _]] = matrix.0;
+    let bias = if (b == 0.0 && d == 0.0) || (a == 0.0 && e == 0.0) {
+        Affine::IDENTITY
+    } else {
+        sample_bias(down_scale_factor, downsample.is_nearest())
+    };
+    let matrix = matrix.then(Affine::translation(-min_x, -min_y)).then(bias);
+    let pivot = matrix.apply((fwidth / 2.0, fheight / 2.0));
+
+    // Angle of the transformed X axis
+    let [[a, _, _], [d, _, _]] = matrix.0;
+    let rotation = d.atan2(a).to_degrees().rem_euclid(360.0);
+
+    Some(RotationResult::new(transformed, rotation, matrix, pivot))
+}
+
+// Upper left corner and size of the bounding box of the transformed image, snapped to whole pixels
+fn transformed_bounds(fwidth: f64, fheight: f64, matrix: Affine) -> (f64, f64, usize, usize) {
+    let corners = [(0.0, 0.0), (fwidth, 0.0), (0.0, fheight), (fwidth, fheight)]
+        .map(|corner| matrix.apply(corner));
+
+    // Ignore tiny rounding errors so they don't add a whole row or column
+    const EPSILON: f64 = 1e-9;
+    let min_x = corners
+        .iter()
+        .map(|(x, _)| *x)
+        .fold(f64::INFINITY, f64::min);
+    let min_y = corners
+        .iter()
+        .map(|(_, y)| *y)
+        .fold(f64::INFINITY, f64::min);
+    let max_x = corners
+        .iter()
+        .map(|(x, _)| *x)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let max_y = corners
+        .iter()
+        .map(|(_, y)| *y)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (min_x, min_y) = ((min_x + EPSILON).floor(), (min_y + EPSILON).floor());
+    let (max_x, max_y) = ((max_x - EPSILON).ceil(), (max_y - EPSILON).ceil());
+
+    (
+        min_x,
+        min_y,
+        (max_x - min_x).max(0.0) as usize,
+        (max_y - min_y).max(0.0) as usize,
+    )
+}
+
+// Sample an upscaled image of `(result_width, result_height)` and reduce each block of
+// `down_scale_factor * down_scale_factor` samples to a single pixel with the downsampler
+//
+// Samples returning `None` are outside of the source and count as the empty color,
+// the nearest sample of a block is the last one inside the source
+#[inline(always)]
+fn resample<'a, P, F, D>(
+    sample: F,
+    empty_color: &'a P,
+    (result_width, result_height): (usize, usize),
+    down_scale_factor: usize,
+    downsample: &D,
+) -> (usize, usize, Vec<P>)
+where
+    P: Clone,
+    F: Fn(usize, usize) -> Option<&'a P>,
+    D: Downsampler<P>,
+{
+    // Size after downscaling
+    let result_buffer_width = result_width.div_ceil(down_scale_factor);
+    let result_buffer_height = result_height.div_ceil(down_scale_factor);
+
+    // Write every sample directly, the last one inside the source is kept for each pixel
+    if downsample.is_nearest() {
+        let mut resampled = vec![empty_color.clone(); result_buffer_width * result_buffer_height];
+        for y in 0..result_height {
+            let y_dst_pos = y / down_scale_factor;
+
+            for x in 0..result_width {
+                if let Some(pixel) = sample(x, y) {
+                    let x_dst_pos = x / down_scale_factor;
+                    resampled[y_dst_pos * result_buffer_width + x_dst_pos] = pixel.clone();
+                }
+            }
+        }
+
+        return (result_buffer_width, result_buffer_height, resampled);
+    }
+
+    let mut resampled = Vec::with_capacity(result_buffer_width * result_buffer_height);
+    let mut samples = Vec::with_capacity(down_scale_factor * down_scale_factor);
+
+    for y_dst_pos in 0..result_buffer_height {
+        for x_dst_pos in 0..result_buffer_width {
+            // Collect all samples of the upscaled image inside this pixel
+            samples.clear();
+            let mut nearest = empty_color;
+            for sub_y in 0..down_scale_factor {
+                let y = y_dst_pos * down_scale_factor + sub_y;
+                if y >= result_height {
+                    break;
+                }
+
+                for sub_x in 0..down_scale_factor {
+                    let x = x_dst_pos * down_scale_factor + sub_x;
+                    if x >= result_width {
+                        break;
+                    }
+
+                    let pixel = match sample(x, y) {
+                        Some(pixel) => {
+                            nearest = pixel;
+
+                            pixel
+                        }
+                        None => empty_color,
+                    };
+                    samples.push((sub_x, sub_y, pixel));
+                }
+            }
+
+            resampled.push(downsample.downsample(&Block::new(
+                &samples,
+                down_scale_factor,
+                nearest,
+                empty_color,
+            )));
+        }
+    }
+
+    (result_buffer_width, result_buffer_height, resampled)
+}
+
+// Upper left corner and size of the bounding box of the rotated image, relative to the rotation origin
+//
+// Expects the rotation to be in the 0.0-360.0 range
+fn rotated_bounds(fwidth: f64, fheight: f64, rotation: f64) -> (f64, f64, f64, f64) {
+    let radians = rotation.to_radians();
+    let sin = radians.sin();
+    let cos = radians.cos();
+
     let p1 = (-fheight * sin, fheight * cos);
     let p2 = (fwidth * cos - fheight * sin, fheight * cos + fwidth * sin);
     let p3 = (fwidth * cos, fwidth * sin);
@@ -65,48 +370,80 @@ where
     let result_width = (max_x.abs() - min_x).ceil();
     let result_height = (max_y.abs() - min_y).ceil();
 
-    // Rotated size with scaling
-    let fscale = down_scale_factor as f64;
-    let result_buffer_width = (result_width / fscale).ceil() as usize;
-    let result_buffer_height = (result_height / fscale).ceil() as usize;
-
-    // Create the downscaled and rotated result buffer
-    let mut rotated = vec![empty_color.clone(); result_buffer_width * result_buffer_height];
-
-    for y in 0..result_height as usize {
-        let fy = y as f64;
+    (min_x, min_y, result_width, result_height)
+}
 
-        let y_with_min = fy + min_y;
+// Affine matrix mapping positions in the source to positions in the rotated image
+//
+// This is the inverse of the sampling in `rotate_scaled_with_offset` called with the same arguments,
+// the source is the upscaled image and the result is in the downscaled rotated image
+//
+// `nearest` is whether the downsampler is nearest-neighbour, see `sample_bias`
+pub fn scaled_rotation_matrix(
+    width: usize,
+    height: usize,
+    rotation: f64,
+    scale: f64,
+    down_scale_factor: usize,
+    (offset_x, offset_y): (f64, f64),
+    nearest: bool,
+) -> Affine {
+    let rotation = rotation.rem_euclid(360.0);
+    let fscale = down_scale_factor as f64;
 
-        let y_min_sin = y_with_min * sin;
-        let y_min_cos = y_with_min * cos;
+    // The fast path doesn't use the offset
+    let offset = if is_fast_path(rotation, scale) {
+        Affine::IDENTITY
+    } else {
+        Affine::translation(-offset_x, -offset_y)
+    };
 
-        for x in 0..result_width as usize {
-            let fx = x as f64;
+    // Only rotations that aren't axis aligned sample in between the pixels of the source
+    let bias = if rotation % 90.0 == 0.0 {
+        Affine::IDENTITY
+    } else {
+        sample_bias(down_scale_factor, nearest)
+    };
 
-            let x_with_min = fx + min_x;
+    let (min_x, min_y, _, _) =
+        rotated_bounds(width as f64 * scale, height as f64 * scale, rotation);
 
-            let x_min_sin = x_with_min * sin;
-            let x_min_cos = x_with_min * cos;
+    offset
+        .then(Affine::rotation(rotation))
+        .then(Affine::scale(scale, scale))
+        .then(Affine::translation(-min_x, -min_y))
+        .then(Affine::scale(1.0 / fscale, 1.0 / fscale))
+        .then(bias)
+}
 
-            let source_x = x_min_cos + y_min_sin;
-            let source_y = y_min_cos - x_min_sin;
+// Translation moving positions to the output pixel showing them after resampling
+//
+// The samples of an output pixel start at its upper left corner, nearest-neighbour keeps the last
+// one inside the source which is almost a whole pixel further, other downsamplers are assumed to
+// show the center of the block, the center of an output pixel maps back to where it's sampled
+fn sample_bias(down_scale_factor: usize, nearest: bool) -> Affine {
+    let fscale = down_scale_factor as f64;
+    let kept = if nearest {
+        fscale - 1.0
+    } else {
+        (fscale - 1.0) / 2.0
+    };
+    let bias = 0.5 - kept / fscale;
 
-            if source_x >= 0.0 && source_x < fwidth && source_y >= 0.0 && source_y < fheight {
-                let x_dst_pos = fx / fscale;
-                let y_dst_pos = fy / fscale;
-                rotated[y_dst_pos as usize * result_buffer_width + x_dst_pos as usize] =
-                    buf[source_y as usize * width + source_x as usize].clone();
-            }
-        }
-    }
+    Affine::translation(bias, bias)
+}
 
-    (result_buffer_width, result_buffer_height, rotated)
+// Whether the image can be rotated by only moving pixels around
+//
+// Expects the rotation to be in the 0.0-360.0 range
+fn is_fast_path(rotation: f64, scale: f64) -> bool {
+    rotation % 90.0 == 0.0 && scale == 1.0
 }
 
-pub fn rotate90<P>(buf: &[P], width: usize, height: usize) -> (usize, usize, Vec<P>)
+pub fn rotate90<P, I>(image: &I) -> (usize, usize, Vec<P>)
 where
     P: Clone,
+    I: ImageView<P>,
 {
     // 1, 2, 3
     // 4, 5, 6
@@ -115,19 +452,19 @@ where
     // 5, 2
     // 6, 3
 
-    let mut rotated = buf.to_vec();
-    for y in 0..height {
-        for x in 0..width {
-            rotated[x * height + (height - y - 1)] = buf[y * width + x].clone();
-        }
-    }
+    let (width, height) = image.size();
+    let rotated = (0..width)
+        .flat_map(|x| (0..height).rev().map(move |y| image.pixel(x, y)))
+        .cloned()
+        .collect();
 
     (height, width, rotated)
 }
 
-pub fn rotate180<P>(buf: &[P], width: usize, height: usize) -> (usize, usize, Vec<P>)
+pub fn rotate180<P, I>(image: &I) -> (usize, usize, Vec<P>)
 where
     P: Clone,
+    I: ImageView<P>,
 {
     // 1, 2, 3
     // 4, 5, 6
@@ -135,15 +472,20 @@ where
     // 6, 5, 4
     // 3, 2, 1
 
-    let mut rotated = buf.to_vec();
-    rotated.reverse();
+    let (width, height) = image.size();
+    let rotated = (0..height)
+        .rev()
+        .flat_map(|y| image.row(y).iter().rev())
+        .cloned()
+        .collect();
 
     (width, height, rotated)
 }
 
-pub fn rotate270<P>(buf: &[P], width: usize, height: usize) -> (usize, usize, Vec<P>)
+pub fn rotate270<P, I>(image: &I) -> (usize, usize, Vec<P>)
 where
     P: Clone,
+    I: ImageView<P>,
 {
     // 1, 2, 3
     // 4, 5, 6
@@ -152,38 +494,294 @@ where
     // 2, 5
     // 1, 2
 
-    let (width, height, rotated) = rotate90(buf, width, height);
+    let (width, height, rotated) = rotate90(image);
+
+    rotate180(&SubImage::packed(&rotated, width, height))
+}
+
+pub fn flip_horizontal<P, I>(image: &I) -> (usize, usize, Vec<P>)
+where
+    P: Clone,
+    I: ImageView<P>,
+{
+    // 1, 2, 3
+    // 4, 5, 6
+    // ->
+    // 3, 2, 1
+    // 6, 5, 4
+
+    let (width, height) = image.size();
+    let flipped = (0..height)
+        .flat_map(|y| image.row(y).iter().rev())
+        .cloned()
+        .collect();
+
+    (width, height, flipped)
+}
+
+pub fn flip_vertical<P, I>(image: &I) -> (usize, usize, Vec<P>)
+where
+    P: Clone,
+    I: ImageView<P>,
+{
+    // 1, 2, 3
+    // 4, 5, 6
+    // ->
+    // 4, 5, 6
+    // 1, 2, 3
+
+    let (width, height) = image.size();
+    let flipped = (0..height)
+        .rev()
+        .flat_map(|y| image.row(y))
+        .cloned()
+        .collect();
 
-    rotate180(&rotated, width, height)
+    (width, height, flipped)
 }
 
-pub fn downscale<P>(buf: &[P], width: usize, height: usize, factor: usize) -> (usize, usize, Vec<P>)
+pub fn transpose<P, I>(image: &I) -> (usize, usize, Vec<P>)
+where
+    P: Clone,
+    I: ImageView<P>,
+{
+    // 1, 2, 3
+    // 4, 5, 6
+    // ->
+    // 1, 4
+    // 2, 5
+    // 3, 6
+
+    let (width, height) = image.size();
+    let transposed = (0..width)
+        .flat_map(|x| (0..height).map(move |y| image.pixel(x, y)))
+        .cloned()
+        .collect();
+
+    (height, width, transposed)
+}
+
+// Copy the area at `(x, y)` with the size `(crop_width, crop_height)` from the image
+//
+// Parts of the area outside of the image are filled with the empty color
+pub(crate) fn crop<P>(
+    buf: &[P],
+    empty_color: &P,
+    width: usize,
+    height: usize,
+    (x, y): (isize, isize),
+    (crop_width, crop_height): (usize, usize),
+) -> RotationResult<P>
 where
     P: Clone,
 {
+    let mut cropped = vec![empty_color.clone(); crop_width * crop_height];
+
+    for crop_y in 0..crop_height {
+        let Some(source_y) = y
+            .checked_add_unsigned(crop_y)
+            .and_then(|y| usize::try_from(y).ok())
+            .filter(|y| *y < height)
+        else {
+            continue;
+        };
+
+        // Range of the row that overlaps with the image, saturating for positions far outside of it
+        let start = 0_isize.saturating_sub(x).clamp(0, crop_width as isize) as usize;
+        let end = (width as isize)
+            .saturating_sub(x)
+            .clamp(0, crop_width as isize) as usize;
+        if start >= end {
+            continue;
+        }
+
+        let source_start = source_y * width + (x + start as isize) as usize;
+        cropped[crop_y * crop_width + start..crop_y * crop_width + end]
+            .clone_from_slice(&buf[source_start..source_start + (end - start)]);
+    }
+
+    RotationResult::new(
+        (crop_width, crop_height, cropped),
+        0.0,
+        Affine::translation(-(x as f64), -(y as f64)),
+        (
+            width as f64 / 2.0 - x as f64,
+            height as f64 / 2.0 - y as f64,
+        ),
+    )
+}
+
+// Find the smallest area `(x, y, width, height)` containing all pixels that aren't the empty color
+//
+// Returns `None` when all pixels are empty
+pub(crate) fn trim_bounds<P>(
+    buf: &[P],
+    empty_color: &P,
+    width: usize,
+    height: usize,
+) -> Option<(usize, usize, usize, usize)>
+where
+    P: Eq,
+{
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for y in 0..height {
+        let row = &buf[y * width..(y + 1) * width];
+        let Some(first) = row.iter().position(|pixel| pixel != empty_color) else {
+            continue;
+        };
+        // There's at least a single non-empty pixel in the row
+        let last = row
+            .iter()
+            .rposition(|pixel| pixel != empty_color)
+            .unwrap_or(first);
+
+        bounds = Some(match bounds {
+            Some((min_x, min_y, max_x, _)) => (min_x.min(first), min_y, max_x.max(last), y),
+            None => (first, y, last, y),
+        });
+    }
+
+    bounds.map(|(min_x, min_y, max_x, max_y)| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+pub fn downscale<P>(
+    buf: &[P],
+    width: usize,
+    height: usize,
+    factor: usize,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Clone,
+{
+    SubImage::sized(buf, width, height)?;
+    if factor == 0 {
+        return Err(Error::InvalidScale);
+    }
+
     let new_width = width / factor;
     let new_height = height / factor;
 
-    let mut scaled = vec![buf[0].clone(); new_width * new_height];
+    let mut scaled = Vec::with_capacity(new_width * new_height);
 
     for y in 0..new_height {
-        let y_row = y * new_width;
         let y_row_scaled = y * factor * width;
         for x in 0..new_width {
-            scaled[y_row + x] = buf[y_row_scaled + x * factor].clone();
+            scaled.push(buf[y_row_scaled + x * factor].clone());
+        }
+    }
+
+    Ok(downscale_result(
+        (new_width, new_height, scaled),
+        width,
+        height,
+        factor,
+    ))
+}
+
+// Same as `downscale` but each block is reduced to a single pixel with the downsampler
+//
+// The nearest sample of a block is the upper left one, like in `downscale`
+pub fn downscale_with_downsample<P, D, I>(
+    image: &I,
+    empty_color: &P,
+    factor: usize,
+    downsample: &D,
+) -> RotationResult<P>
+where
+    P: Clone,
+    D: Downsampler<P>,
+    I: ImageView<P>,
+{
+    let (width, height) = image.size();
+    let new_width = width / factor;
+    let new_height = height / factor;
+
+    let mut scaled = Vec::with_capacity(new_width * new_height);
+    let mut samples = Vec::with_capacity(factor * factor);
+
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let nearest = image.pixel(x * factor, y * factor);
+            if downsample.is_nearest() {
+                scaled.push(nearest.clone());
+                continue;
+            }
+
+            samples.clear();
+            for sub_y in 0..factor {
+                let row = image.row(y * factor + sub_y);
+                for sub_x in 0..factor {
+                    samples.push((sub_x, sub_y, &row[x * factor + sub_x]));
+                }
+            }
+
+            scaled.push(downsample.downsample(&Block::new(&samples, factor, nearest, empty_color)));
         }
     }
 
-    (new_width, new_height, scaled)
+    downscale_result((new_width, new_height, scaled), width, height, factor)
+}
+
+// Wrap the downscaled pixels with how the source maps to them
+fn downscale_result<P>(
+    downscaled: (usize, usize, Vec<P>),
+    width: usize,
+    height: usize,
+    factor: usize,
+) -> RotationResult<P> {
+    let fscale = factor as f64;
+
+    RotationResult::new(
+        downscaled,
+        0.0,
+        Affine::scale(1.0 / fscale, 1.0 / fscale),
+        (width as f64 / 2.0 / fscale, height as f64 / 2.0 / fscale),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::TieBreak;
+
+    #[test]
+    fn rotation_offset() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        assert_eq!(
+            rotate(&buf, &0, 3, 3, 30.0, 1)?,
+            rotate_with_offset(&buf, &0, 3, 3, 30.0, 1, (0.0, 0.0), &Downsample::Nearest)?
+        );
+
+        // Shifting by a whole pixel samples the neighbouring pixels instead
+        let shifted =
+            rotate_with_offset(&buf, &0, 3, 3, 30.0, 1, (1.0, 0.0), &Downsample::Nearest)?;
+        assert_eq!(shifted.size(), (5, 5));
+        assert_ne!(shifted.pixels(), rotate(&buf, &0, 3, 3, 30.0, 1)?.pixels());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        let buf = [1, 2, 3, 4];
+        assert_eq!(
+            rotate(&buf, &0, 2, 2, 30.0, 0).unwrap_err(),
+            Error::InvalidScale
+        );
+        assert_eq!(
+            rotate(&buf, &0, 3, 2, 30.0, 1).unwrap_err(),
+            Error::ImageSizeMismatch
+        );
+        assert_eq!(downscale(&buf, 2, 2, 0).unwrap_err(), Error::InvalidScale);
+        assert_eq!(
+            downscale(&buf, 2, 3, 1).unwrap_err(),
+            Error::ImageSizeMismatch
+        );
+    }
 
     #[test]
     fn rotation_90_deg() {
-        let (w, h, new) = rotate90(&[1, 2, 3, 4, 5, 6], 3, 2);
+        let (w, h, new) = rotate90(&SubImage::packed(&[1, 2, 3, 4, 5, 6], 3, 2));
         assert_eq!(w, 2);
         assert_eq!(h, 3);
         assert_eq!(new, [4, 1, 5, 2, 6, 3]);
@@ -191,7 +789,7 @@ mod tests {
 
     #[test]
     fn rotation_180_deg() {
-        let (w, h, new) = rotate180(&[1, 2, 3, 4, 5, 6], 3, 2);
+        let (w, h, new) = rotate180(&SubImage::packed(&[1, 2, 3, 4, 5, 6], 3, 2));
         assert_eq!(w, 3);
         assert_eq!(h, 2);
         assert_eq!(new, [6, 5, 4, 3, 2, 1]);
@@ -199,9 +797,239 @@ mod tests {
 
     #[test]
     fn rotation_270_deg() {
-        let (w, h, new) = rotate270(&[1, 2, 3, 4, 5, 6], 3, 2);
+        let (w, h, new) = rotate270(&SubImage::packed(&[1, 2, 3, 4, 5, 6], 3, 2));
         assert_eq!(w, 2);
         assert_eq!(h, 3);
         assert_eq!(new, [3, 6, 2, 5, 1, 4]);
     }
+
+    #[test]
+    fn flip_and_transpose() {
+        let (w, h, new) = flip_horizontal(&SubImage::packed(&[1, 2, 3, 4, 5, 6], 3, 2));
+        assert_eq!((w, h), (3, 2));
+        assert_eq!(new, [3, 2, 1, 6, 5, 4]);
+
+        let (w, h, new) = flip_vertical(&SubImage::packed(&[1, 2, 3, 4, 5, 6], 3, 2));
+        assert_eq!((w, h), (3, 2));
+        assert_eq!(new, [4, 5, 6, 1, 2, 3]);
+
+        let (w, h, new) = transpose(&SubImage::packed(&[1, 2, 3, 4, 5, 6], 3, 2));
+        assert_eq!((w, h), (2, 3));
+        assert_eq!(new, [1, 4, 2, 5, 3, 6]);
+    }
+
+    #[test]
+    fn downscale_majority() -> Result<(), Error> {
+        #[rustfmt::skip]
+        let buf = [
+            1, 2, 0, 0,
+            2, 2, 0, 3,
+            1, 1, 0, 0,
+            2, 2, 3, 3,
+        ];
+
+        let (w, h, new) = downscale(&buf, 4, 4, 2)?.into();
+        assert_eq!((w, h), (2, 2));
+        assert_eq!(new, [1, 0, 1, 0]);
+
+        let new = downscale_with_downsample(
+            &SubImage::packed(&buf, 4, 4),
+            &0,
+            2,
+            &Downsample::Majority(TieBreak::Center),
+        );
+        assert_eq!(new.pixels(), [2, 0, 1, 0]);
+
+        // Ties between empty and non-empty colors
+        let new = downscale_with_downsample(
+            &SubImage::packed(&buf, 4, 4),
+            &0,
+            2,
+            &Downsample::Majority(TieBreak::NonEmpty),
+        );
+        assert_eq!(new.pixels(), [2, 0, 1, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_majority() -> Result<(), Error> {
+        let buf = [1, 1, 1, 2, 1, 1, 1, 1, 1];
+        let majority = rotate_with_offset(
+            &buf,
+            &0,
+            3,
+            3,
+            30.0,
+            1,
+            (0.0, 0.0),
+            &Downsample::Majority(TieBreak::Center),
+        )?;
+
+        // Without downscaling every block contains a single sample
+        assert_eq!(majority, rotate(&buf, &0, 3, 3, 30.0, 1)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_nearest_blocks() -> Result<(), Error> {
+        // Picks the nearest sample from collected blocks instead of writing samples directly
+        struct BlockNearest;
+
+        impl Downsampler<i32> for BlockNearest {
+            fn downsample(&self, block: &Block<'_, i32>) -> i32 {
+                *block.nearest()
+            }
+        }
+
+        let buf = (0..64).collect::<Vec<_>>();
+        for rotation in [0.0, 30.0, 90.0, 137.0] {
+            assert_eq!(
+                rotate_with_offset(&buf, &-1, 8, 8, rotation, 4, (0.0, 0.0), &BlockNearest)?
+                    .pixels(),
+                rotate(&buf, &-1, 8, 8, rotation, 4)?.pixels()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_matrix_matches_rotation() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4, 5, 6];
+        for rotation in [90.0, 180.0, 270.0] {
+            let rotated = rotate(&buf, &0, 3, 2, rotation, 1)?;
+            assert_eq!(rotated.rotation(), rotation);
+            for y in 0..2 {
+                for x in 0..3 {
+                    let (dst_x, dst_y) = rotated.matrix().apply((x as f64 + 0.5, y as f64 + 0.5));
+                    assert_eq!(
+                        rotated.pixels()[dst_y as usize * rotated.width() + dst_x as usize],
+                        buf[y * 3 + x]
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_scaled() -> Result<(), Error> {
+        let buf = [1, 2, 3, 4];
+        let scaled = rotate_scaled_with_offset(
+            &SubImage::packed(&buf, 2, 2),
+            &0,
+            0.0,
+            2.0,
+            1,
+            (0.0, 0.0),
+            &Downsample::Nearest,
+        );
+        assert_eq!(scaled.size(), (4, 4));
+        #[rustfmt::skip]
+        assert_eq!(scaled.pixels(), [
+            1, 1, 2, 2,
+            1, 1, 2, 2,
+            3, 3, 4, 4,
+            3, 3, 4, 4,
+        ]);
+        assert_eq!(scaled.matrix().apply((1.0, 1.0)), (2.0, 2.0));
+
+        // Scaling down skips pixels
+        let scaled = rotate_scaled_with_offset(
+            &SubImage::packed(scaled.pixels(), 4, 4),
+            &0,
+            0.0,
+            0.5,
+            1,
+            (0.0, 0.0),
+            &Downsample::Nearest,
+        );
+        assert_eq!(scaled.pixels(), buf);
+
+        // Scaling while rotating resizes the rotated bounds
+        let rotated = rotate(&buf, &0, 2, 2, 30.0, 1)?;
+        let scaled = rotate_scaled_with_offset(
+            &SubImage::packed(&buf, 2, 2),
+            &0,
+            30.0,
+            3.0,
+            1,
+            (0.0, 0.0),
+            &Downsample::Nearest,
+        );
+        assert!(scaled.width() >= rotated.width() * 3 - 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_sub_image() -> Result<(), Error> {
+        #[rustfmt::skip]
+        let atlas = [
+            9, 9, 9, 9,
+            9, 1, 2, 9,
+            9, 3, 4, 9,
+            9, 5, 6, 9,
+        ];
+        let image = SubImage::from_rect(&atlas, 4, (1, 1, 2, 3)).unwrap();
+        let packed = [1, 2, 3, 4, 5, 6];
+
+        assert_eq!(rotate90(&image), rotate90(&SubImage::packed(&packed, 2, 3)));
+        assert_eq!(transpose(&image), (3, 2, vec![1, 3, 5, 2, 4, 6]));
+        assert_eq!(
+            rotate_scaled_with_offset(&image, &0, 30.0, 1.0, 1, (0.0, 0.0), &Downsample::Nearest),
+            rotate(&packed, &0, 2, 3, 30.0, 1)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn crop_and_pad() {
+        let buf = [1, 2, 3, 4, 5, 6];
+
+        let cropped = crop(&buf, &0, 3, 2, (1, 0), (2, 2));
+        assert_eq!(cropped.pixels(), [2, 3, 5, 6]);
+
+        let padded = crop(&buf, &0, 3, 2, (-1, -1), (5, 4));
+        #[rustfmt::skip]
+        assert_eq!(padded.pixels(), [
+            0, 0, 0, 0, 0,
+            0, 1, 2, 3, 0,
+            0, 4, 5, 6, 0,
+            0, 0, 0, 0, 0,
+        ]);
+        assert_eq!(padded.offset(), (-1.0, -1.0));
+
+        let outside = crop(&buf, &0, 3, 2, (5, 0), (2, 1));
+        assert_eq!(outside.pixels(), [0, 0]);
+
+        // Positions at the limits don't overflow
+        for position in [
+            (isize::MIN, 0),
+            (isize::MAX, 0),
+            (0, isize::MIN),
+            (0, isize::MAX),
+        ] {
+            let far = crop(&buf, &0, 3, 2, position, (2, 2));
+            assert_eq!(far.pixels(), [0, 0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn trim_empty_borders() {
+        #[rustfmt::skip]
+        let buf = [
+            0, 0, 0, 0,
+            0, 0, 1, 0,
+            0, 2, 0, 0,
+            0, 0, 0, 0,
+        ];
+        assert_eq!(trim_bounds(&buf, &0, 4, 4), Some((1, 1, 2, 2)));
+        assert_eq!(trim_bounds(&[1, 2, 3, 4], &0, 2, 2), Some((0, 0, 2, 2)));
+        assert_eq!(trim_bounds(&[0; 6], &0, 3, 2), None);
+    }
 }
@@ -1,141 +1,132 @@
+use crate::{
+    color::{Exact, Similarity},
+    upscale::upscale_packed,
+    Affine, Border, Error, ImageView, RotationResult,
+};
+
 // Algorithm for fast upscaling of pixel art sprites
-pub fn scale2x<P>(buf: &[P], width: usize, height: usize) -> (usize, usize, Vec<P>)
+pub fn scale2x<P>(buf: &[P], width: usize, height: usize) -> Result<RotationResult<P>, Error>
 where
     P: Eq + Clone,
 {
+    scale2x_with_similarity(buf, width, height, &Exact)
+}
+
+// Same as `scale2x` but neighbouring pixels are compared with a custom predicate
+pub fn scale2x_with_similarity<P, S>(
+    buf: &[P],
+    width: usize,
+    height: usize,
+    similarity: &S,
+) -> Result<RotationResult<P>, Error>
+where
+    P: Clone,
+    S: Similarity<P>,
+{
+    upscale_packed(buf, width, height, |image, border, pixel| {
+        scale2x_with_border(image, similarity, border, pixel)
+    })
+}
+
+// Same as `scale2x_with_similarity` but neighbours outside of the image are resolved with a border mode
+//
+// The empty color is only used by `Border::Empty`
+pub fn scale2x_with_border<P, S, I>(
+    image: &I,
+    similarity: &S,
+    border: Border,
+    empty_color: &P,
+) -> RotationResult<P>
+where
+    P: Clone,
+    S: Similarity<P>,
+    I: ImageView<P>,
+{
+    let (width, height) = image.size();
     let width2 = width * 2;
     let height2 = height * 2;
 
-    let mut scaled = vec![buf[0].clone(); width2 * height2];
+    // Every pixel is overwritten by the algorithm
+    let mut scaled = vec![empty_color.clone(); width2 * height2];
 
     // Apply the algorithm to the center
-    for y in 1..height - 1 {
-        let y_this = y * width;
+    for y in 1..height.saturating_sub(1) {
+        let (row_prev, row_this, row_next) = (image.row(y - 1), image.row(y), image.row(y + 1));
         let scaled_y = y * 2 * width2;
 
-        for x in 1..width - 1 {
-            let pos = y_this + x;
+        for x in 1..width.saturating_sub(1) {
             apply_scale2x_block(
+                similarity,
                 &mut scaled,
                 scaled_y + x * 2,
                 width2,
                 (
                     // Center
-                    &buf[pos],
+                    &row_this[x],
                     // Up
-                    &buf[pos - width],
+                    &row_prev[x],
                     // Left
-                    &buf[pos - 1],
+                    &row_this[x - 1],
                     // Down
-                    &buf[pos + width],
+                    &row_next[x],
                     // Right
-                    &buf[pos + 1],
+                    &row_this[x + 1],
                 ),
             );
         }
-
-        let y_prev = y_this - width;
-        let y_next = y_this + width;
-
-        // Left most column
-        let p = &buf[y_this];
-        apply_scale2x_block(
-            &mut scaled,
-            scaled_y,
-            width2,
-            (p, &buf[y_prev], p, &buf[y_next], &buf[y_this + 1]),
-        );
-
-        // Right most column
-        let p = &buf[y_next - 1];
-        apply_scale2x_block(
-            &mut scaled,
-            scaled_y + width2 - 2,
-            width2,
-            (
-                p,
-                &buf[y_this - 1],
-                &buf[y_next - 2],
-                &buf[y_next + width - 1],
-                p,
-            ),
-        );
     }
 
-    for x in 1..width - 1 {
-        // Apply the algorithm to the first row
-        let p = &buf[x];
-        apply_scale2x_block(
-            &mut scaled,
-            x * 2,
-            width2,
-            (p, p, &buf[x - 1], &buf[x + width], &buf[x + 1]),
-        );
+    // Apply the algorithm to the borders, where neighbours can be outside of the image
+    let pixel = |x: usize, y: usize, dx: isize, dy: isize| {
+        border.pixel(image, (x as isize + dx, y as isize + dy), empty_color)
+    };
+    for y in 0..height {
+        // Only the first and last pixel of rows in between are on the border
+        let step = if y == 0 || y == height - 1 {
+            1
+        } else {
+            width.saturating_sub(1).max(1)
+        };
 
-        // Apply the algorithm to the last row
-        let pos = (height - 1) * width + x;
-        let p = &buf[pos];
-        let scaled_y_this = ((height - 1) * 2) * width2;
-        apply_scale2x_block(
-            &mut scaled,
-            scaled_y_this + x * 2,
-            width2,
-            (p, &buf[pos - width], &buf[pos - 1], p, &buf[pos + 1]),
-        );
+        for x in (0..width).step_by(step) {
+            apply_scale2x_block(
+                similarity,
+                &mut scaled,
+                y * 2 * width2 + x * 2,
+                width2,
+                (
+                    image.pixel(x, y),
+                    pixel(x, y, 0, -1),
+                    pixel(x, y, -1, 0),
+                    pixel(x, y, 0, 1),
+                    pixel(x, y, 1, 0),
+                ),
+            );
+        }
     }
 
-    // Apply the algorithms to the corners
-
-    // Top left corner
-    let p = &buf[0];
-    apply_scale2x_block(&mut scaled, 0, width2, (p, p, p, &buf[width], &buf[1]));
-
-    // Top right corner
-    let x_right = width - 1;
-    let p = &buf[x_right];
-    apply_scale2x_block(
-        &mut scaled,
-        width2 - 2,
-        width2,
-        (p, p, &buf[x_right - 1], &buf[x_right + width], p),
-    );
-
-    // Bottom left corner
-    let y_bottom = (height - 1) * width;
-    let p = &buf[y_bottom];
-    apply_scale2x_block(
-        &mut scaled,
-        (height2 - 2) * width2,
-        width2,
-        (p, &buf[y_bottom - width], p, p, &buf[y_bottom + 1]),
-    );
-
-    // Bottom right corner
-    let y_bottom_right = y_bottom + x_right;
-    let p = &buf[y_bottom_right];
-    apply_scale2x_block(
-        &mut scaled,
-        (height2 - 2) * width2 + width2 - 2,
-        width2,
-        (
-            p,
-            &buf[y_bottom_right - width],
-            &buf[y_bottom_right - 1],
-            p,
-            p,
-        ),
-    );
-
-    (width2, height2, scaled)
+    RotationResult::new(
+        (width2, height2, scaled),
+        0.0,
+        Affine::scale(2.0, 2.0),
+        (width as f64, height as f64),
+    )
 }
 
 // Apply the block on the buffer
 #[inline(always)]
-fn apply_scale2x_block<P>(scaled: &mut [P], pos: usize, width: usize, pixels: (&P, &P, &P, &P, &P))
-where
-    P: Eq + Clone,
+fn apply_scale2x_block<P, S>(
+    similarity: &S,
+    scaled: &mut [P],
+    pos: usize,
+    width: usize,
+    pixels: (&P, &P, &P, &P, &P),
+) where
+    P: Clone,
+    S: Similarity<P>,
 {
-    let block_pixels = calculate_scale2x_block(pixels.0, pixels.1, pixels.2, pixels.3, pixels.4);
+    let block_pixels =
+        calculate_scale2x_block(similarity, pixels.0, pixels.1, pixels.2, pixels.3, pixels.4);
     scaled[pos] = block_pixels.0;
     scaled[pos + 1] = block_pixels.1;
     scaled[pos + width] = block_pixels.2;
@@ -144,30 +135,40 @@ where
 
 // Convert a single pixel to an upscaled 2x2 block
 #[inline(always)]
-fn calculate_scale2x_block<P>(center: &P, up: &P, left: &P, down: &P, right: &P) -> (P, P, P, P)
+fn calculate_scale2x_block<P, S>(
+    similarity: &S,
+    center: &P,
+    up: &P,
+    left: &P,
+    down: &P,
+    right: &P,
+) -> (P, P, P, P)
 where
-    P: Eq + Clone,
+    P: Clone,
+    S: Similarity<P>,
 {
+    let eq = |a, b| similarity.is_similar(a, b);
+
     (
-        (if left == up && left != down && up != right {
+        (if eq(left, up) && !eq(left, down) && !eq(up, right) {
             up
         } else {
             center
         })
         .clone(),
-        (if up == right && up != left && right != down {
+        (if eq(up, right) && !eq(up, left) && !eq(right, down) {
             right
         } else {
             center
         })
         .clone(),
-        (if down == left && down != right && left != up {
+        (if eq(down, left) && !eq(down, right) && !eq(left, up) {
             left
         } else {
             center
         })
         .clone(),
-        (if right == down && right != up && down != left {
+        (if eq(right, down) && !eq(right, up) && !eq(down, left) {
             down
         } else {
             center
@@ -179,17 +180,18 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::SubImage;
 
     #[test]
-    fn scale2x_test() {
+    fn scale2x_test() -> Result<(), Error> {
         let buf = [1, 2, 3, 4];
-        let (w, h, new) = scale2x(&buf, 2, 2);
+        let (w, h, new) = scale2x(&buf, 2, 2)?.into();
         assert_eq!(w, 4);
         assert_eq!(h, 4);
         assert_eq!(new, [1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]);
 
         let buf = [1, 2, 3, 4, 5, 6, 7, 8, 9];
-        let (_, _, new) = scale2x(&buf, 3, 3);
+        let new = scale2x(&buf, 3, 3)?.into_pixels();
         let mut cmp = Vec::<usize>::new();
         cmp.extend([1, 1, 2, 2, 3, 3].iter());
         cmp.extend([1, 1, 2, 2, 3, 3].iter());
@@ -200,10 +202,122 @@ mod tests {
         assert_eq!(new, cmp);
 
         let buf = [1, 2, 3, 4, 5, 6];
-        let (_, _, new) = scale2x(&buf, 3, 2);
+        let new = scale2x(&buf, 3, 2)?.into_pixels();
         assert_eq!(
             new,
             [1, 1, 2, 2, 3, 3, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 4, 4, 5, 5, 6, 6]
         );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scale2x_degenerate_test() -> Result<(), Error> {
+        assert_eq!(scale2x(&[1], 1, 1)?.into_pixels(), [1; 4]);
+        assert_eq!(
+            scale2x(&[1, 2], 2, 1)?.into_pixels(),
+            [1, 1, 2, 2, 1, 1, 2, 2]
+        );
+        assert_eq!(
+            scale2x(&[1, 2], 1, 2)?.into_pixels(),
+            [1, 1, 1, 1, 2, 2, 2, 2]
+        );
+
+        // Sizes that don't match the buffer are rejected
+        assert_eq!(scale2x::<i32>(&[], 0, 0).unwrap_err(), Error::ZeroWidth);
+        assert_eq!(scale2x::<i32>(&[], 1, 0).unwrap_err(), Error::EmptyBuffer);
+        assert_eq!(scale2x(&[1, 2, 3], 0, 3).unwrap_err(), Error::ZeroWidth);
+        assert_eq!(
+            scale2x(&[1, 2, 3], 2, 2).unwrap_err(),
+            Error::ImageSizeMismatch
+        );
+        assert_eq!(
+            scale2x(&[1, 2, 3], 3, 2).unwrap_err(),
+            Error::ImageSizeMismatch
+        );
+
+        // Views without a width still have rows in between the first and the last
+        let image = SubImage::packed(&[1, 2, 3], 0, 3);
+        assert_eq!(
+            scale2x_with_border(&image, &Exact, Border::Clamp, &0).size(),
+            (0, 6)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scale2x_sub_image_test() -> Result<(), Error> {
+        #[rustfmt::skip]
+        let atlas = [
+            0, 0, 0, 0, 0,
+            0, 1, 2, 3, 0,
+            0, 4, 5, 6, 0,
+            0, 7, 8, 9, 0,
+        ];
+        let image = SubImage::from_rect(&atlas, 5, (1, 1, 3, 3)).unwrap();
+        let packed = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+
+        // The surrounding pixels of the atlas aren't part of the sprite
+        assert_eq!(
+            scale2x_with_border(&image, &Exact, Border::Clamp, &0),
+            scale2x(&packed, 3, 3)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scale2x_similarity_test() -> Result<(), Error> {
+        // The center pixel is only smoothed when `5` and `6` are treated as the same color
+        let buf = [1, 5, 1, 6, 1, 1, 1, 1, 1];
+        let exact = scale2x(&buf, 3, 3)?.into_pixels();
+        assert_eq!(exact[14], 1);
+
+        let similar = |a: &i32, b: &i32| (a - b).abs() <= 1;
+        let smoothed = scale2x_with_similarity(&buf, 3, 3, &similar)?.into_pixels();
+        assert_eq!(smoothed[14], 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn scale2x_border_test() -> Result<(), Error> {
+        #[rustfmt::skip]
+        let buf = [
+            1, 1, 0,
+            1, 0, 0,
+            0, 0, 2,
+        ];
+        let clamp = scale2x(&buf, 3, 3)?.into_pixels();
+        assert_eq!(
+            scale2x_with_border(&SubImage::packed(&buf, 3, 3), &Exact, Border::Clamp, &0)
+                .into_pixels(),
+            clamp
+        );
+        // Clamped neighbours are the pixel itself so the corner isn't smoothed
+        assert_eq!(clamp[0], 1);
+
+        // Next to the empty color the corner gets rounded
+        let empty = scale2x_with_border(&SubImage::packed(&buf, 3, 3), &Exact, Border::Empty, &0)
+            .into_pixels();
+        assert_eq!(empty[0], 0);
+
+        // Wrapping around is the same as upscaling the middle of the tiled sprite
+        let tiled = (0..81)
+            .map(|i| buf[(i / 9 % 3) * 3 + i % 3])
+            .collect::<Vec<_>>();
+        let tiled = scale2x(&tiled, 9, 9)?.into_pixels();
+        let wrap = scale2x_with_border(&SubImage::packed(&buf, 3, 3), &Exact, Border::Wrap, &0)
+            .into_pixels();
+        for y in 0..6 {
+            assert_eq!(
+                wrap[y * 6..y * 6 + 6],
+                tiled[(y + 6) * 18 + 6..(y + 6) * 18 + 12]
+            );
+        }
+        assert_ne!(wrap, clamp);
+
+        Ok(())
     }
 }
@@ -0,0 +1,105 @@
+use crate::{
+    color::{Exact, Similarity},
+    upscale::{upscale_blocks, upscale_packed},
+    Border, Error, ImageView, RotationResult,
+};
+
+// Scale3x (also known as AdvMAME3x) algorithm for upscaling pixel art sprites
+pub fn scale3x<P>(buf: &[P], width: usize, height: usize) -> Result<RotationResult<P>, Error>
+where
+    P: Eq + Clone,
+{
+    upscale_packed(buf, width, height, |image, border, pixel| {
+        scale3x_with_border(image, &Exact, border, pixel)
+    })
+}
+
+// Same as `scale3x` but neighbouring pixels are compared with a custom predicate
+//
+// Neighbours outside of the image are resolved with the border mode, the empty color is only used by `Border::Empty`
+pub fn scale3x_with_border<P, S, I>(
+    image: &I,
+    similarity: &S,
+    border: Border,
+    empty_color: &P,
+) -> RotationResult<P>
+where
+    P: Clone,
+    S: Similarity<P>,
+    I: ImageView<P>,
+{
+    upscale_blocks(image, border, empty_color, |neighbourhood| {
+        calculate_scale3x_block(similarity, neighbourhood)
+    })
+}
+
+// Convert a single pixel with its 8 neighbours to an upscaled 3x3 block
+#[inline(always)]
+fn calculate_scale3x_block<P, S>(
+    similarity: &S,
+    [[a, b, c], [d, e, f], [g, h, i]]: [[&P; 3]; 3],
+) -> [[P; 3]; 3]
+where
+    P: Clone,
+    S: Similarity<P>,
+{
+    let eq = |a, b| similarity.is_similar(a, b);
+
+    // Nothing to smooth when there's no corner
+    if eq(b, h) || eq(d, f) {
+        return [
+            [e.clone(), e.clone(), e.clone()],
+            [e.clone(), e.clone(), e.clone()],
+            [e.clone(), e.clone(), e.clone()],
+        ];
+    }
+
+    let pick = |condition: bool, pixel: &P| if condition { pixel } else { e }.clone();
+
+    [
+        [
+            pick(eq(d, b), d),
+            pick((eq(d, b) && !eq(e, c)) || (eq(b, f) && !eq(e, a)), b),
+            pick(eq(b, f), f),
+        ],
+        [
+            pick((eq(d, b) && !eq(e, g)) || (eq(d, h) && !eq(e, a)), d),
+            e.clone(),
+            pick((eq(b, f) && !eq(e, i)) || (eq(h, f) && !eq(e, c)), f),
+        ],
+        [
+            pick(eq(d, h), d),
+            pick((eq(d, h) && !eq(e, i)) || (eq(h, f) && !eq(e, g)), h),
+            pick(eq(h, f), f),
+        ],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale3x_test() -> Result<(), Error> {
+        let (w, h, new) = scale3x(&[1, 2], 2, 1)?.into();
+        assert_eq!(w, 6);
+        assert_eq!(h, 3);
+        assert_eq!(new, [1, 1, 1, 2, 2, 2, 1, 1, 1, 2, 2, 2, 1, 1, 1, 2, 2, 2]);
+
+        // Diagonal lines get smoothed
+        #[rustfmt::skip]
+        let buf = [
+            1, 0, 0,
+            0, 1, 0,
+            0, 0, 1,
+        ];
+        let new = scale3x(&buf, 3, 3)?.into_pixels();
+        // The lower left corner of the block right of the upper left pixel is filled by the diagonal
+        assert_eq!(new[2 * 9 + 3], 1);
+        assert_eq!(new[3], 0);
+        // The center of a block always keeps the color
+        assert_eq!(new[4 * 9 + 4], 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,339 @@
+use std::ops::Range;
+
+use crate::{Error, ImageView, ImageViewMut};
+
+/// Rectangle of pixels borrowed from a larger buffer, such as a single sprite in a texture atlas.
+///
+/// Rows are `stride` pixels apart in the buffer, the pixels are read in place without copying them out.
+#[derive(Debug)]
+pub struct SubImage<'a, P> {
+    /// Pixels starting at the upper left corner of the rectangle.
+    buf: &'a [P],
+    /// Horizontal size of the rectangle in pixels.
+    width: usize,
+    /// Vertical size of the rectangle in pixels.
+    height: usize,
+    /// Distance between the start of two rows in pixels.
+    stride: usize,
+}
+
+impl<'a, P> SubImage<'a, P> {
+    /// Borrow a whole buffer with rows of `width` pixels.
+    ///
+    /// The height is derived from the length of the buffer.
+    pub fn new(buf: &'a [P], width: usize) -> Result<Self, Error> {
+        if width == 0 {
+            return Err(Error::ZeroWidth);
+        }
+
+        let len = buf.len();
+        if len == 0 {
+            return Err(Error::EmptyBuffer);
+        }
+        if !len.is_multiple_of(width) {
+            return Err(Error::ImageSizeMismatch);
+        }
+
+        Ok(Self::packed(buf, width, len / width))
+    }
+
+    /// Borrow the rectangle `(x, y, width, height)` of a buffer with rows of `stride` pixels.
+    pub fn from_rect(
+        buf: &'a [P],
+        stride: usize,
+        (x, y, width, height): (usize, usize, usize, usize),
+    ) -> Result<Self, Error> {
+        let range = rect_range(buf.len(), stride, (x, y, width, height))?;
+
+        Ok(Self {
+            buf: &buf[range],
+            width,
+            height,
+            stride,
+        })
+    }
+
+    /// Borrow a tightly packed buffer with the size `(width, height)`, validated like [`SubImage::new`].
+    pub(crate) fn sized(buf: &'a [P], width: usize, height: usize) -> Result<Self, Error> {
+        let image = Self::new(buf, width)?;
+        if image.height != height {
+            return Err(Error::ImageSizeMismatch);
+        }
+
+        Ok(image)
+    }
+
+    /// Borrow a tightly packed buffer of which the size is already known to be correct.
+    pub(crate) fn packed(buf: &'a [P], width: usize, height: usize) -> Self {
+        debug_assert!(buf.len() >= width * height);
+
+        Self {
+            buf,
+            width,
+            height,
+            stride: width,
+        }
+    }
+
+    /// Horizontal size in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Vertical size in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Size as `(width, height)` in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Distance between the start of two rows in the borrowed buffer, in pixels.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Pixels of row `y`.
+    #[inline(always)]
+    pub fn row(&self, y: usize) -> &'a [P] {
+        let start = y * self.stride;
+
+        &self.buf[start..start + self.width]
+    }
+
+    /// Pixel at `(x, y)`.
+    #[inline(always)]
+    pub fn pixel(&self, x: usize, y: usize) -> &'a P {
+        &self.row(y)[x]
+    }
+
+    /// All pixels, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = &'a P> + '_ {
+        (0..self.height).flat_map(|y| self.row(y))
+    }
+
+    /// Copy the pixels into a tightly packed buffer.
+    pub fn to_vec(&self) -> Vec<P>
+    where
+        P: Clone,
+    {
+        self.pixels().cloned().collect()
+    }
+}
+
+// Manually implemented because deriving requires the pixel to be `Copy`
+impl<P> Clone for SubImage<'_, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P> Copy for SubImage<'_, P> {}
+
+impl<P> ImageView<P> for SubImage<'_, P> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline(always)]
+    fn row(&self, y: usize) -> &[P] {
+        SubImage::row(self, y)
+    }
+}
+
+/// Mutable rectangle of pixels borrowed from a larger buffer, such as a single sprite in a texture atlas.
+///
+/// Like [`SubImage`] but the pixels can be written in place.
+#[derive(Debug)]
+pub struct SubImageMut<'a, P> {
+    /// Pixels starting at the upper left corner of the rectangle.
+    buf: &'a mut [P],
+    /// Horizontal size of the rectangle in pixels.
+    width: usize,
+    /// Vertical size of the rectangle in pixels.
+    height: usize,
+    /// Distance between the start of two rows in pixels.
+    stride: usize,
+}
+
+impl<'a, P> SubImageMut<'a, P> {
+    /// Mutably borrow a whole buffer with rows of `width` pixels.
+    ///
+    /// The height is derived from the length of the buffer.
+    pub fn new(buf: &'a mut [P], width: usize) -> Result<Self, Error> {
+        let height = SubImage::new(buf, width)?.height();
+
+        Ok(Self {
+            buf,
+            width,
+            height,
+            stride: width,
+        })
+    }
+
+    /// Mutably borrow the rectangle `(x, y, width, height)` of a buffer with rows of `stride` pixels.
+    pub fn from_rect(
+        buf: &'a mut [P],
+        stride: usize,
+        (x, y, width, height): (usize, usize, usize, usize),
+    ) -> Result<Self, Error> {
+        let range = rect_range(buf.len(), stride, (x, y, width, height))?;
+
+        Ok(Self {
+            buf: &mut buf[range],
+            width,
+            height,
+            stride,
+        })
+    }
+
+    /// Distance between the start of two rows in the borrowed buffer, in pixels.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Read-only view of the same pixels.
+    pub fn as_sub_image(&self) -> SubImage<'_, P> {
+        SubImage {
+            buf: self.buf,
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+        }
+    }
+}
+
+impl<P> ImageView<P> for SubImageMut<'_, P> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline(always)]
+    fn row(&self, y: usize) -> &[P] {
+        let start = y * self.stride;
+
+        &self.buf[start..start + self.width]
+    }
+}
+
+impl<P> ImageViewMut<P> for SubImageMut<'_, P> {
+    #[inline(always)]
+    fn row_mut(&mut self, y: usize) -> &mut [P] {
+        let start = y * self.stride;
+
+        &mut self.buf[start..start + self.width]
+    }
+}
+
+/// Range of a buffer with `len` pixels and rows of `stride` pixels covering the rectangle `(x, y, width, height)`.
+fn rect_range(
+    len: usize,
+    stride: usize,
+    (x, y, width, height): (usize, usize, usize, usize),
+) -> Result<Range<usize>, Error> {
+    if width == 0 || stride == 0 {
+        return Err(Error::ZeroWidth);
+    }
+    if height == 0 {
+        return Err(Error::EmptyBuffer);
+    }
+
+    if x.checked_add(width).is_none_or(|right| right > stride) {
+        return Err(Error::InvalidRect);
+    }
+
+    // Index of the upper left and one past the lower right pixel of the rectangle
+    let start = y
+        .checked_mul(stride)
+        .and_then(|start| start.checked_add(x))
+        .ok_or(Error::InvalidRect)?;
+    let end = (height - 1)
+        .checked_mul(stride)
+        .and_then(|end| end.checked_add(start))
+        .and_then(|end| end.checked_add(width))
+        .ok_or(Error::InvalidRect)?;
+    if end > len {
+        return Err(Error::InvalidRect);
+    }
+
+    Ok(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_image() -> Result<(), Error> {
+        #[rustfmt::skip]
+        let buf = [
+            0, 0, 0, 0,
+            0, 1, 2, 0,
+            0, 3, 4, 0,
+        ];
+
+        let image = SubImage::from_rect(&buf, 4, (1, 1, 2, 2))?;
+        assert_eq!(image.size(), (2, 2));
+        assert_eq!(image.row(1), [3, 4]);
+        assert_eq!(*image.pixel(1, 0), 2);
+        assert_eq!(image.to_vec(), [1, 2, 3, 4]);
+
+        // The last row doesn't need the full stride
+        let image = SubImage::from_rect(&buf[..11], 4, (1, 1, 2, 2))?;
+        assert_eq!(image.to_vec(), [1, 2, 3, 4]);
+
+        assert_eq!(
+            SubImage::from_rect(&buf, 4, (3, 0, 2, 1)).unwrap_err(),
+            Error::InvalidRect
+        );
+        assert_eq!(
+            SubImage::from_rect(&buf, 4, (0, 2, 1, 2)).unwrap_err(),
+            Error::InvalidRect
+        );
+        assert_eq!(
+            SubImage::from_rect(&buf, 4, (0, 0, 0, 2)).unwrap_err(),
+            Error::ZeroWidth
+        );
+        assert_eq!(
+            SubImage::new(&buf, 5).unwrap_err(),
+            Error::ImageSizeMismatch
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sub_image_mut() -> Result<(), Error> {
+        let mut buf = [0; 12];
+
+        let mut image = SubImageMut::from_rect(&mut buf, 4, (1, 1, 2, 2))?;
+        image.row_mut(0).copy_from_slice(&[1, 2]);
+        *image.pixel_mut(1, 1) = 4;
+        assert_eq!(image.to_vec(), [1, 2, 0, 4]);
+        assert_eq!(image.as_sub_image().row(1), [0, 4]);
+
+        #[rustfmt::skip]
+        assert_eq!(buf, [
+            0, 0, 0, 0,
+            0, 1, 2, 0,
+            0, 0, 4, 0,
+        ]);
+
+        assert_eq!(
+            SubImageMut::from_rect(&mut buf, 4, (2, 0, 3, 1)).unwrap_err(),
+            Error::InvalidRect
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use crate::{
+    eagle::eagle_with_border, epx::epx_with_border, scale2x::scale2x_with_border,
+    scale3x::scale3x_with_border, xbr::xbr_with_border, Affine, Border, Error, ImageView,
+    RgbaPixel, RotationResult, Similarity, SubImage, Upscale,
+};
+
+/// Pixel art upscaling algorithm applied before a sprite is rotated.
+///
+/// Implement this to try other algorithms than the ones provided, see [`crate::RotspriteOptions::with_upscale`] for using it.
+pub trait Upscaler<P> {
+    /// Size multiplier of the upscaled image in each dimension.
+    ///
+    /// Must be at least `1`, rotating returns [`crate::Error::ZeroFactor`] otherwise.
+    fn factor(&self) -> usize;
+
+    /// Upscale a sprite, which can be any [`ImageView`] such as a part of a larger buffer.
+    ///
+    /// The returned pixels must be tightly packed with the size `(width * factor, height * factor)`.
+    /// Algorithms comparing colors should use `similarity` to decide which colors are equal.
+    /// Neighbours outside of the sprite should be resolved with `border`, see [`Border::pixel`], `empty_color` is the color used by [`Border::Empty`].
+    fn upscale<S, I>(&self, image: &I, similarity: &S, border: Border, empty_color: &P) -> Vec<P>
+    where
+        S: Similarity<P>,
+        I: ImageView<P>;
+}
+
+impl<P> Upscaler<P> for Upscale
+where
+    P: Clone,
+{
+    fn factor(&self) -> usize {
+        Upscale::factor(*self)
+    }
+
+    fn upscale<S, I>(&self, image: &I, similarity: &S, border: Border, empty_color: &P) -> Vec<P>
+    where
+        S: Similarity<P>,
+        I: ImageView<P>,
+    {
+        let (scale2x_passes, scale3x_passes) = self.passes();
+        let factors =
+            std::iter::repeat_n(2, scale2x_passes).chain(std::iter::repeat_n(3, scale3x_passes));
+
+        let mut scaled: Option<RotationResult<P>> = None;
+        for factor in factors {
+            // Read the first pass directly from the source so it doesn't have to be copied
+            scaled = Some(match &scaled {
+                Some(scaled) => upscale_pass(factor, scaled, similarity, border, empty_color),
+                None => upscale_pass(factor, image, similarity, border, empty_color),
+            });
+        }
+
+        scaled.map_or_else(|| image.to_vec(), RotationResult::into_pixels)
+    }
+}
+
+/// Upscale a tightly packed buffer with clamped borders, the size is validated like [`SubImage::new`].
+///
+/// Used by the algorithm functions that take a buffer instead of an [`ImageView`].
+pub(crate) fn upscale_packed<P, F>(
+    buf: &[P],
+    width: usize,
+    height: usize,
+    upscale: F,
+) -> Result<RotationResult<P>, Error>
+where
+    F: FnOnce(&SubImage<'_, P>, Border, &P) -> RotationResult<P>,
+{
+    let image = SubImage::sized(buf, width, height)?;
+
+    // Clamping never uses the empty color, any pixel will do
+    Ok(upscale(&image, Border::Clamp, &buf[0]))
+}
+
+/// Upscale every pixel to a block of `N` by `N` pixels calculated from its `K` by `K` neighbourhood.
+///
+/// The neighbourhood is centered on the pixel, neighbours outside of the image are resolved with the border mode.
+pub(crate) fn upscale_blocks<P, I, F, const K: usize, const N: usize>(
+    image: &I,
+    border: Border,
+    empty_color: &P,
+    block: F,
+) -> RotationResult<P>
+where
+    P: Clone,
+    I: ImageView<P>,
+    F: Fn([[&P; K]; K]) -> [[P; N]; N],
+{
+    let (width, height) = image.size();
+    let mut scaled = Vec::with_capacity(width * N * height * N);
+
+    // Neighbour relative to a pixel, outside of the image it's resolved with the border mode
+    let radius = (K / 2) as isize;
+    let pixel = |x: usize, y: usize, dx: isize, dy: isize| {
+        border.pixel(image, (x as isize + dx, y as isize + dy), empty_color)
+    };
+
+    for y in 0..height {
+        // Calculate all blocks of the row first, then write them out line by line
+        let blocks = (0..width)
+            .map(|x| {
+                block(std::array::from_fn(|row| {
+                    std::array::from_fn(|column| {
+                        pixel(x, y, column as isize - radius, row as isize - radius)
+                    })
+                }))
+            })
+            .collect::<Vec<_>>();
+
+        for line in 0..N {
+            for block in &blocks {
+                scaled.extend_from_slice(&block[line]);
+            }
+        }
+    }
+
+    let factor = N as f64;
+    RotationResult::new(
+        (width * N, height * N, scaled),
+        0.0,
+        Affine::scale(factor, factor),
+        (width as f64 * factor / 2.0, height as f64 * factor / 2.0),
+    )
+}
+
+/// Apply a single scale2x or scale3x pass of [`Upscale`].
+fn upscale_pass<P, S, I>(
+    factor: usize,
+    image: &I,
+    similarity: &S,
+    border: Border,
+    empty_color: &P,
+) -> RotationResult<P>
+where
+    P: Clone,
+    S: Similarity<P>,
+    I: ImageView<P>,
+{
+    if factor == 2 {
+        scale2x_with_border(image, similarity, border, empty_color)
+    } else {
+        scale3x_with_border(image, similarity, border, empty_color)
+    }
+}
+
+/// Apply the scale2x algorithm once, also known as AdvMAME2x.
+///
+/// Same as [`Upscale::X2`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Scale2x;
+
+impl<P> Upscaler<P> for Scale2x
+where
+    P: Clone,
+{
+    fn factor(&self) -> usize {
+        2
+    }
+
+    fn upscale<S, I>(&self, image: &I, similarity: &S, border: Border, empty_color: &P) -> Vec<P>
+    where
+        S: Similarity<P>,
+        I: ImageView<P>,
+    {
+        scale2x_with_border(image, similarity, border, empty_color).into_pixels()
+    }
+}
+
+/// Apply the EPX algorithm once.
+///
+/// Scale2x is based on this, it fills more corners which results in bolder diagonals.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Epx;
+
+impl<P> Upscaler<P> for Epx
+where
+    P: Clone,
+{
+    fn factor(&self) -> usize {
+        2
+    }
+
+    fn upscale<S, I>(&self, image: &I, similarity: &S, border: Border, empty_color: &P) -> Vec<P>
+    where
+        S: Similarity<P>,
+        I: ImageView<P>,
+    {
+        epx_with_border(image, similarity, border, empty_color).into_pixels()
+    }
+}
+
+/// Apply the Eagle algorithm once.
+///
+/// Corners only get smoothed when all three neighbours in that direction are equal, which keeps more of the original shapes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Eagle;
+
+impl<P> Upscaler<P> for Eagle
+where
+    P: Clone,
+{
+    fn factor(&self) -> usize {
+        2
+    }
+
+    fn upscale<S, I>(&self, image: &I, similarity: &S, border: Border, empty_color: &P) -> Vec<P>
+    where
+        S: Similarity<P>,
+        I: ImageView<P>,
+    {
+        eagle_with_border(image, similarity, border, empty_color).into_pixels()
+    }
+}
+
+/// Edge-directed upscaling based on the xBR algorithm.
+///
+/// Edges are detected with a color distance over a 5x5 neighbourhood, which gives smoother results than scale2x on sprites with many colors.
+/// Colors are never blended so the pixel-art palette is kept, this requires pixels implementing [`RgbaPixel`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Xbr {
+    /// Upscale 2 times, like 2xBR.
+    #[default]
+    X2,
+    /// Upscale 4 times by applying the 2x level twice.
+    X4,
+}
+
+impl<P> Upscaler<P> for Xbr
+where
+    P: RgbaPixel + Clone,
+{
+    fn factor(&self) -> usize {
+        match self {
+            Xbr::X2 => 2,
+            Xbr::X4 => 4,
+        }
+    }
+
+    fn upscale<S, I>(&self, image: &I, similarity: &S, border: Border, empty_color: &P) -> Vec<P>
+    where
+        S: Similarity<P>,
+        I: ImageView<P>,
+    {
+        let scaled = xbr_with_border(image, similarity, border, empty_color);
+        match self {
+            Xbr::X2 => scaled.into_pixels(),
+            Xbr::X4 => xbr_with_border(&scaled, similarity, border, empty_color).into_pixels(),
+        }
+    }
+}
+
+/// Apply an upscaling algorithm multiple times.
+///
+/// For example `Repeat(Eagle, 3)` upscales 8 times like [`Upscale::X8`] does with scale2x.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Repeat<U>(pub U, pub usize);
+
+impl<P, U> Upscaler<P> for Repeat<U>
+where
+    P: Clone,
+    U: Upscaler<P>,
+{
+    fn factor(&self) -> usize {
+        // Saturate so sizes that can't be allocated are rejected instead of overflowing
+        self.0
+            .factor()
+            .saturating_pow(u32::try_from(self.1).unwrap_or(u32::MAX))
+    }
+
+    fn upscale<S, I>(&self, image: &I, similarity: &S, border: Border, empty_color: &P) -> Vec<P>
+    where
+        S: Similarity<P>,
+        I: ImageView<P>,
+    {
+        let Repeat(upscaler, passes) = self;
+        let factor = upscaler.factor();
+
+        let mut scaled = image.to_vec();
+        let (mut width, mut height) = image.size();
+        for _ in 0..*passes {
+            scaled = upscaler.upscale(
+                &SubImage::packed(&scaled, width, height),
+                similarity,
+                border,
+                empty_color,
+            );
+            width *= factor;
+            height *= factor;
+        }
+
+        scaled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Exact;
+
+    #[test]
+    fn upscalers_match_factor() {
+        let buf = [1, 2, 3, 4, 5, 6];
+        let image = SubImage::packed(&buf, 3, 2);
+
+        assert_eq!(Scale2x.upscale(&image, &Exact, Border::Clamp, &0).len(), 24);
+        assert_eq!(Epx.upscale(&image, &Exact, Border::Clamp, &0).len(), 24);
+        assert_eq!(Eagle.upscale(&image, &Exact, Border::Clamp, &0).len(), 24);
+
+        let rgba = SubImage::packed(&[1_u32, 2, 3], 3, 1);
+        assert_eq!(Xbr::X2.upscale(&rgba, &Exact, Border::Clamp, &0).len(), 12);
+        assert_eq!(Xbr::X4.upscale(&rgba, &Exact, Border::Clamp, &0).len(), 48);
+
+        let repeated = Repeat(Scale2x, 3);
+        assert_eq!(Upscaler::<i32>::factor(&repeated), 8);
+        // Pass counts beyond `u32` saturate instead of wrapping around
+        assert_eq!(
+            Upscaler::<i32>::factor(&Repeat(Scale2x, (u32::MAX as usize).saturating_add(1))),
+            usize::MAX
+        );
+        assert_eq!(
+            repeated.upscale(&image, &Exact, Border::Clamp, &0),
+            Upscale::X8.upscale(&image, &Exact, Border::Clamp, &0)
+        );
+        assert_eq!(Upscale::X1.upscale(&image, &Exact, Border::Clamp, &0), buf);
+    }
+}
@@ -0,0 +1,85 @@
+/// Read access to the pixels of an image, row by row.
+///
+/// Implement it for your own image types to rotate or upscale them without copying them into a buffer first.
+/// Implemented for [`crate::SubImage`], [`crate::SubImageMut`] and [`crate::RotationResult`].
+pub trait ImageView<P> {
+    /// Horizontal size in pixels.
+    fn width(&self) -> usize;
+
+    /// Vertical size in pixels.
+    fn height(&self) -> usize;
+
+    /// Pixels of row `y`, exactly [`ImageView::width`] long.
+    ///
+    /// Only called with `y` smaller than [`ImageView::height`].
+    fn row(&self, y: usize) -> &[P];
+
+    /// Size as `(width, height)` in pixels.
+    fn size(&self) -> (usize, usize) {
+        (self.width(), self.height())
+    }
+
+    /// Pixel at `(x, y)`.
+    #[inline(always)]
+    fn pixel(&self, x: usize, y: usize) -> &P {
+        &self.row(y)[x]
+    }
+
+    /// Copy the pixels into a tightly packed buffer, row by row.
+    fn to_vec(&self) -> Vec<P>
+    where
+        P: Clone,
+    {
+        (0..self.height())
+            .flat_map(|y| self.row(y).iter().cloned())
+            .collect()
+    }
+}
+
+/// Write access to the pixels of an image, row by row.
+pub trait ImageViewMut<P>: ImageView<P> {
+    /// Mutable pixels of row `y`, exactly [`ImageView::width`] long.
+    ///
+    /// Only called with `y` smaller than [`ImageView::height`].
+    fn row_mut(&mut self, y: usize) -> &mut [P];
+
+    /// Mutable pixel at `(x, y)`.
+    #[inline(always)]
+    fn pixel_mut(&mut self, x: usize, y: usize) -> &mut P {
+        &mut self.row_mut(y)[x]
+    }
+}
+
+/// View of an image with the order of the rows optionally reversed, mirroring it top and bottom without copying.
+pub(crate) struct RowOrder<'a, I> {
+    image: &'a I,
+    reversed: bool,
+}
+
+impl<'a, I> RowOrder<'a, I> {
+    /// Wrap an image, reversing the order of its rows when `reversed` is set.
+    pub(crate) fn new(image: &'a I, reversed: bool) -> Self {
+        Self { image, reversed }
+    }
+}
+
+impl<P, I> ImageView<P> for RowOrder<'_, I>
+where
+    I: ImageView<P>,
+{
+    fn width(&self) -> usize {
+        self.image.width()
+    }
+
+    fn height(&self) -> usize {
+        self.image.height()
+    }
+
+    fn row(&self, y: usize) -> &[P] {
+        if self.reversed {
+            self.image.row(self.image.height() - 1 - y)
+        } else {
+            self.image.row(y)
+        }
+    }
+}
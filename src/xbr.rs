@@ -0,0 +1,159 @@
+use crate::{
+    color::{Exact, RgbaPixel, Similarity},
+    upscale::{upscale_blocks, upscale_packed},
+    Border, Error, ImageView, RotationResult,
+};
+
+// Edge-directed upscaling based on the xBR algorithm by Hyllian
+//
+// For every corner of a pixel the strength of the edges in both diagonal directions is weighted
+// with a color distance in the 5x5 neighbourhood, when the edge through the corner is the weakest
+// the corner takes the closest neighbouring color
+//
+// Colors are never blended so no new colors are introduced
+pub fn xbr<P>(buf: &[P], width: usize, height: usize) -> Result<RotationResult<P>, Error>
+where
+    P: RgbaPixel + Eq + Clone,
+{
+    upscale_packed(buf, width, height, |image, border, pixel| {
+        xbr_with_border(image, &Exact, border, pixel)
+    })
+}
+
+// Same as `xbr` but pixels are compared with a custom predicate to decide whether there's an edge
+//
+// Neighbours outside of the image are resolved with the border mode, the empty color is only used by `Border::Empty`
+pub fn xbr_with_border<P, S, I>(
+    image: &I,
+    similarity: &S,
+    border: Border,
+    empty_color: &P,
+) -> RotationResult<P>
+where
+    P: RgbaPixel + Clone,
+    S: Similarity<P>,
+    I: ImageView<P>,
+{
+    upscale_blocks(image, border, empty_color, |neighbourhood| {
+        calculate_xbr_block(similarity, &neighbourhood)
+    })
+}
+
+// Convert a single pixel with its 5x5 neighbourhood to an upscaled 2x2 block
+#[inline(always)]
+fn calculate_xbr_block<P, S>(similarity: &S, neighbourhood: &[[&P; 5]; 5]) -> [[P; 2]; 2]
+where
+    P: RgbaPixel + Clone,
+    S: Similarity<P>,
+{
+    [
+        [
+            calculate_xbr_corner(similarity, neighbourhood, -1, -1),
+            calculate_xbr_corner(similarity, neighbourhood, -1, 1),
+        ],
+        [
+            calculate_xbr_corner(similarity, neighbourhood, 1, -1),
+            calculate_xbr_corner(similarity, neighbourhood, 1, 1),
+        ],
+    ]
+}
+
+// Calculate the color of a single corner, the direction of the corner is `(dir_y, dir_x)`
+//
+// The neighbourhood is mirrored so the formulas can always be written for the lower right corner:
+//
+//        A1 B1 C1
+//     A0 A  B  C  C4
+//     D0 D  E  F  F4
+//     G0 G  H  I  I4
+//        G5 H5 I5
+#[inline(always)]
+fn calculate_xbr_corner<P, S>(
+    similarity: &S,
+    neighbourhood: &[[&P; 5]; 5],
+    dir_y: isize,
+    dir_x: isize,
+) -> P
+where
+    P: RgbaPixel + Clone,
+    S: Similarity<P>,
+{
+    let at = |y: isize, x: isize| neighbourhood[(2 + y * dir_y) as usize][(2 + x * dir_x) as usize];
+
+    let (b, c) = (at(-1, 0), at(-1, 1));
+    let (d, e, f, f4) = (at(0, -1), at(0, 0), at(0, 1), at(0, 2));
+    let (g, h, i, i4) = (at(1, -1), at(1, 0), at(1, 1), at(1, 2));
+    let (h5, i5) = (at(2, 0), at(2, 1));
+
+    // There's no edge through the corner when the pixel continues in either direction
+    if similarity.is_similar(e, f) || similarity.is_similar(e, h) {
+        return e.clone();
+    }
+
+    // Strength of the edge along the corner and of the edge crossing it
+    let along =
+        distance(e, c) + distance(e, g) + distance(i, f4) + distance(i, h5) + 4.0 * distance(h, f);
+    let across =
+        distance(h, d) + distance(h, i5) + distance(f, i4) + distance(f, b) + 4.0 * distance(e, i);
+
+    if along < across {
+        if distance(e, f) <= distance(e, h) {
+            f.clone()
+        } else {
+            h.clone()
+        }
+    } else {
+        e.clone()
+    }
+}
+
+// Perceptual distance between two colors in the YUV color space, alpha is weighted like the luminance
+#[inline(always)]
+fn distance<P>(a: &P, b: &P) -> f64
+where
+    P: RgbaPixel,
+{
+    let [r1, g1, b1, a1] = a.to_rgba().map(f64::from);
+    let [r2, g2, b2, a2] = b.to_rgba().map(f64::from);
+    let (r, g, b, a) = (r1 - r2, g1 - g2, b1 - b2, a1 - a2);
+
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let u = -0.169 * r - 0.331 * g + 0.5 * b;
+    let v = 0.5 * r - 0.419 * g - 0.081 * b;
+
+    48.0 * y.abs() + 7.0 * u.abs() + 6.0 * v.abs() + 48.0 * a.abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xbr_test() -> Result<(), Error> {
+        let (w, h, new) = xbr(&[1_u32, 2, 3, 4], 2, 2)?.into();
+        assert_eq!((w, h), (4, 4));
+        // Outer corners have no neighbours to form an edge with
+        assert_eq!([new[0], new[3], new[12], new[15]], [1, 2, 3, 4]);
+
+        // The staircase of a diagonal line gets smoothed
+        const X: u32 = 0xFF_FF_FF_FF;
+        #[rustfmt::skip]
+        let buf = [
+            0, 0, 0, 0, X,
+            0, 0, 0, X, X,
+            0, 0, X, X, X,
+            0, X, X, X, X,
+            X, X, X, X, X,
+        ];
+        let new = xbr(&buf, 5, 5)?.into_pixels();
+        // Lower right corner of the empty pixel left of the center
+        assert_eq!(new[5 * 10 + 3], X);
+        // Upper left corner of the filled center pixel
+        assert_eq!(new[4 * 10 + 4], 0);
+        // Pixels away from the edge don't change
+        assert_eq!(new[0], 0);
+        assert_eq!(new[9 * 10 + 9], X);
+
+        Ok(())
+    }
+}